@@ -0,0 +1,145 @@
+use crate::models::{Correction, DeliveryItem, FieldChange, FileValidationError};
+use crate::validation::item_finding;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CORRECTIONS_FILE: &str = "corrections.json";
+
+/// 获取修正记录文件路径 (与配置文件同目录)
+fn get_corrections_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("ana")
+        .join(CORRECTIONS_FILE)
+}
+
+/// 加载修正记录
+pub fn load_corrections() -> Vec<Correction> {
+    fs::read_to_string(get_corrections_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 保存修正记录
+pub fn save_corrections(corrections: &[Correction]) -> Result<()> {
+    let path = get_corrections_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("创建配置目录失败")?;
+    }
+    let content = serde_json::to_string_pretty(corrections).context("序列化修正记录失败")?;
+    fs::write(&path, content).context("保存修正记录失败")?;
+    Ok(())
+}
+
+/// 将新修正合并到同一文件同一行的已有修正中 (新修正中填写的字段覆盖旧值，未填写的保留)
+/// 文件内容已变化时旧修正失效，直接用新修正替换
+pub fn merge_correction(corrections: &mut Vec<Correction>, correction: Correction) {
+    let Some(existing) = corrections
+        .iter_mut()
+        .find(|c| c.file == correction.file && c.row == correction.row)
+    else {
+        corrections.push(correction);
+        return;
+    };
+
+    if existing.fingerprint != correction.fingerprint {
+        *existing = correction;
+        return;
+    }
+
+    existing.customer = correction.customer.or(existing.customer.take());
+    existing.date = correction.date.or(existing.date.take());
+    existing.product_name = correction.product_name.or(existing.product_name.take());
+    existing.quantity = correction.quantity.or(existing.quantity);
+    existing.unit_price = correction.unit_price.or(existing.unit_price);
+    if !correction.note.is_empty() && !existing.note.contains(&correction.note) {
+        existing.note = if existing.note.is_empty() {
+            correction.note
+        } else {
+            format!("{}；{}", existing.note, correction.note)
+        };
+    }
+    existing.created_at = correction.created_at;
+}
+
+/// 计算文件内容指纹 (SHA-256)
+pub fn file_fingerprint(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("无法读取文件: {:?}", path))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// 将修正记录应用到同一文件的条目上 (按文件指纹 + 行号匹配)
+/// 文件内容已变化的修正不会应用，返回提示让用户重新核对
+pub fn apply_corrections(
+    items: &mut [DeliveryItem],
+    fingerprint: &str,
+    corrections: &[Correction],
+) -> Vec<FileValidationError> {
+    let mut findings = Vec::new();
+
+    for item in items.iter_mut() {
+        if let Some(c) = corrections
+            .iter()
+            .find(|c| c.fingerprint == fingerprint && c.row == item.source_row)
+        {
+            apply_correction(item, c);
+        } else if let Some(c) = corrections
+            .iter()
+            .find(|c| c.file == item.source_file && c.row == item.source_row)
+        {
+            findings.push(item_finding(
+                "stale_correction",
+                item,
+                format!(
+                    "文件已变更，第 {} 行的修正 ({}) 未应用，请重新核对",
+                    c.row, c.created_at
+                ),
+            ));
+        }
+    }
+
+    findings
+}
+
+/// 应用单条修正并记录修改前后的值
+fn apply_correction(item: &mut DeliveryItem, c: &Correction) {
+    fn record(item: &mut DeliveryItem, field: &str, original: String, corrected: String) {
+        if original != corrected {
+            item.corrections.push(FieldChange {
+                field: field.to_string(),
+                original,
+                corrected,
+            });
+        }
+    }
+
+    if let Some(customer) = &c.customer {
+        record(item, "客户", item.customer.clone(), customer.clone());
+        item.customer = customer.clone();
+    }
+    if let Some(date) = &c.date {
+        record(item, "日期", item.date.clone(), date.clone());
+        item.date = date.clone();
+    }
+    if let Some(product_name) = &c.product_name {
+        record(item, "货名", item.product_name.clone(), product_name.clone());
+        item.product_name = product_name.clone();
+    }
+    if let Some(quantity) = c.quantity {
+        record(item, "数量", item.quantity.to_string(), quantity.to_string());
+        item.quantity = quantity;
+        item.billing_quantity = quantity;
+        item.unreadable_quantity.clear();
+    }
+    if let Some(unit_price) = c.unit_price {
+        record(item, "单价", item.unit_price.to_string(), unit_price.to_string());
+        item.unit_price = unit_price;
+        item.price_missing = false;
+    }
+    if c.quantity.is_some() || c.unit_price.is_some() {
+        item.amount = (item.quantity * item.unit_price * 100.0).round() / 100.0;
+    }
+}
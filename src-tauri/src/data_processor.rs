@@ -1,23 +1,79 @@
-use crate::excel_parser::extract_delivery_data;
-use crate::models::{DeliveryItem, FileValidationError, SummaryItem};
-use anyhow::Result;
+use crate::corrections::apply_corrections;
+use crate::duplicates::resolve_duplicates;
+use crate::error::{AppError, AppResult, IoAction};
+use crate::exclusions::{excluded, VoidList};
+use crate::models::{
+    AppConfig, BillingCycle, Correction, DeliveryItem, DuplicateGroup, ExcludedDocument,
+    GroupingStrategy, FileAction, FileValidationError,
+    PriceAnomaly, RejectedRow, SequenceIssue, Severity, SummaryItem,
+};
+use crate::pricing::apply_pricing;
+use crate::scan_cache::ScanCache;
+use crate::sequence_check::{check_order_sequences, compile_order_formats};
+use crate::spec_parser::gauge_label;
+use crate::validation::{apply_rule_settings, check_items, finding, item_finding};
+use crate::workers::Progress;
 use chrono::Datelike;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+/// 扫描到的文件: (文件路径, 客户类型)
+pub type TypedFile = (PathBuf, String);
+
+/// 是否为需要处理的工作簿 (.xls/.xlsx，跳过 Excel 打开文件时生成的 ~$ 临时文件)
+pub fn is_workbook_file(path: &Path) -> bool {
+    let is_excel = path
+        .extension()
+        .map(|ext| {
+            let ext = ext.to_string_lossy().to_lowercase();
+            ext == "xls" || ext == "xlsx"
+        })
+        .unwrap_or(false);
+    let is_temp = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .starts_with("~$");
+    is_excel && !is_temp
+}
+
+/// 文件的客户类型: 根目录下第一级子目录名，直接放在根目录下的文件为 "默认"
+pub fn customer_type_of(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let mut components = relative.components();
+    match (components.next(), components.next()) {
+        (Some(first), Some(_)) => first.as_os_str().to_string_lossy().to_string(),
+        _ => "默认".to_string(),
+    }
+}
+
+/// 原始数据目录的规范化绝对路径
+/// 扫描、监视和台账都只使用此路径下的文件路径，同一文件在各处的路径保持一致
+pub fn raw_data_root(raw_data_path: &str) -> AppResult<PathBuf> {
+    let path = Path::new(raw_data_path);
+    std::fs::canonicalize(path).map_err(|e| AppError::io(IoAction::ScanDir, path, e))
+}
+
 /// 扫描目录中的所有 Excel 文件，并根据一级子目录确定客户类型
 /// 目录结构: Root -> Type (现金客户/月结客户) -> ... -> Files
-pub fn scan_excel_files(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+/// `dir` 为 `raw_data_root` 返回的规范化路径，返回的文件路径均在其下
+/// 命中作废清单的文件不会返回，而是列在排除记录中
+pub fn scan_excel_files(
+    dir: &Path,
+    void_list: &VoidList,
+) -> AppResult<(Vec<TypedFile>, Vec<ExcludedDocument>)> {
     let mut files = Vec::new();
+    let mut excluded_docs = Vec::new();
 
     if !dir.exists() {
-        return Ok(files);
+        return Ok((files, excluded_docs));
     }
 
     // 读取根目录下的第一级子目录作为客户类型
-    for entry in std::fs::read_dir(dir)? {
-        let entry = entry?;
+    let scan_error = |e: std::io::Error| AppError::io(IoAction::ScanDir, dir, e);
+    for entry in std::fs::read_dir(dir).map_err(scan_error)? {
+        let entry = entry.map_err(scan_error)?;
         let path = entry.path();
         
         if path.is_dir() {
@@ -44,13 +100,11 @@ pub fn scan_excel_files(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
                             .to_string_lossy()
                             .starts_with("~$")
                         {
-                            // 确保返回的是绝对路径
-                            let abs_path = if file_path.is_absolute() {
-                                file_path.to_path_buf()
+                            if let Some(reason) = void_list.match_file(file_path) {
+                                excluded_docs.push(excluded(&file_path.to_string_lossy(), "", reason));
                             } else {
-                                std::env::current_dir().unwrap_or_default().join(file_path)
-                            };
-                            files.push((abs_path, type_name.clone()));
+                                files.push((file_path.to_path_buf(), type_name.clone()));
+                            }
                         }
                     }
                 }
@@ -59,8 +113,12 @@ pub fn scan_excel_files(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
              // 如果根目录下直接有文件，归类为 "未分类" 或 "默认"
              if let Some(ext) = path.extension() {
                 let ext_str = ext.to_string_lossy().to_lowercase();
-                if ext_str == "xls" || ext_str == "xlsx" {
-                    if !path.file_name().unwrap_or_default().to_string_lossy().starts_with("~$") {
+                if (ext_str == "xls" || ext_str == "xlsx")
+                    && !path.file_name().unwrap_or_default().to_string_lossy().starts_with("~$")
+                {
+                    if let Some(reason) = void_list.match_file(&path) {
+                        excluded_docs.push(excluded(&path.to_string_lossy(), "", reason));
+                    } else {
                         files.push((path.to_path_buf(), "默认".to_string()));
                     }
                 }
@@ -68,73 +126,158 @@ pub fn scan_excel_files(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
         }
     }
 
-    Ok(files)
+    Ok((files, excluded_docs))
 }
 
-/// 合并所有送货单数据
-pub fn merge_delivery_data(files: &[(PathBuf, String)]) -> Result<Vec<DeliveryItem>> {
-    let mut all_items = Vec::new();
-
-    for (file, customer_type) in files {
-        match extract_delivery_data(file, customer_type) {
-            Ok(items) => {
-                all_items.extend(items);
-            }
-            Err(e) => {
-                eprintln!("处理文件 {:?} 时出错: {}", file, e);
-            }
-        }
-    }
-
-    Ok(all_items)
+/// 日期无法识别的条目所在分组，不生成对账单
+pub const UNASSIGNED_GROUP: &str = "unassigned";
+
+/// 历史单价比较窗口 (取之前最近的 N 条记录)
+const PRICE_HISTORY_WINDOW: usize = 10;
+/// 至少需要的历史记录数，不足时不做判断
+const PRICE_HISTORY_MIN: usize = 3;
+
+/// 校验结果汇总
+pub struct ValidationReport {
+    /// 通过校验的条目
+    pub items: Vec<DeliveryItem>,
+    /// 校验结果 (已按配置调整严重级别)
+    pub findings: Vec<FileValidationError>,
+    /// 被拒绝的行
+    pub rejected_rows: Vec<RejectedRow>,
+    /// 作废或按设置排除的送货单
+    pub excluded: Vec<ExcludedDocument>,
+    /// 送货单号顺序问题
+    pub sequence_issues: Vec<SequenceIssue>,
+    /// 跨文件内容重复的行
+    pub duplicates: Vec<DuplicateGroup>,
+    /// 历史单价异常
+    pub price_anomalies: Vec<PriceAnomaly>,
+    /// 日期无法识别、待指定日期的条目
+    pub unassigned: Vec<DeliveryItem>,
+    /// 任务被取消，结果只包含取消前处理完的文件
+    pub cancelled: bool,
 }
 
 /// 验证并合并送货单数据
+/// 有错误级别结果的行会被拒绝，同一文件的其他行照常使用；文件级错误或设置为排除的文件整体不使用
+/// `void_list` 为本次扫描编译好的作废清单
 pub fn validate_delivery_data(
     files: &[(PathBuf, String)],
-) -> (
-    Vec<DeliveryItem>,
-    Vec<FileValidationError>,
-    Vec<FileValidationError>,
-) {
+    config: &AppConfig,
+    void_list: &VoidList,
+    corrections: &[Correction],
+    cache: &mut ScanCache,
+    workers: usize,
+    progress: &dyn Progress,
+) -> ValidationReport {
+    let validation = &config.validation;
     let mut all_items = Vec::new();
-    let mut errors = Vec::new();
-    let mut warnings = Vec::new();
+    let mut findings = Vec::new();
+    let mut rejected_rows = Vec::new();
+    let mut excluded_docs = Vec::new();
+    let mut unassigned = Vec::new();
+    // 已作废的行 (不参与生成，但其单号仍计入缺号检查)
+    let mut voided_items = Vec::new();
     // 记录 (客户, 单号) 及其来源文件，用于同客户内的单号查重: (customer, order_no) -> file_path
     let mut order_no_map: HashMap<(String, String), String> = HashMap::new();
 
-    for (file, customer_type) in files {
-        match extract_delivery_data(file, customer_type) {
+    // 设置为排除的文件不解析
+    let (skipped, files): (Vec<_>, Vec<_>) = files.iter().cloned().partition(|(file, _)| {
+        let file_str = file.to_string_lossy();
+        config
+            .file_actions
+            .iter()
+            .any(|a| a.file == file_str && a.action == FileAction::ExcludeFile)
+    });
+    for (file, _) in &skipped {
+        excluded_docs.push(excluded(&file.to_string_lossy(), "", "已按设置排除整个文件".to_string()));
+    }
+
+    // 并行解析 (未变化的文件直接使用缓存的解析结果)，之后按文件顺序逐个校验
+    let parsed_files = cache.parse_all(&files, workers, progress);
+
+    let mut cancelled = progress.is_cancelled();
+
+    progress.start("validate", files.len());
+    for ((file, _), parsed) in files.iter().zip(parsed_files) {
+        // 在文件之间检查取消
+        let Some(parsed) = parsed.filter(|_| !progress.is_cancelled()) else {
+            cancelled = true;
+            break;
+        };
+        let file_str = file.to_string_lossy().to_string();
+        let mut file_findings = Vec::new();
+        let rejected_before = rejected_rows.len();
+
+        match parsed.items {
             Ok(items) => {
+                // 作废的工作表整体排除，作废的送货单号只排除对应的行
+                if let Some(reason) = items
+                    .first()
+                    .and_then(|i| void_list.match_sheet(&i.sheet_name))
+                {
+                    excluded_docs.push(excluded(&file_str, "", reason));
+                    voided_items.extend(items);
+                    progress.advance(&file_str, "excluded");
+                    continue;
+                }
+                let (voided, kept): (Vec<_>, Vec<_>) = items.into_iter().partition(|item| {
+                    match void_list.match_order(&item.customer, &item.delivery_order_no) {
+                        Some(reason) => {
+                            excluded_docs.push(excluded(&file_str, &item.delivery_order_no, reason));
+                            true
+                        }
+                        None => false,
+                    }
+                });
+                voided_items.extend(voided);
+                let mut items = kept;
+                if items.is_empty() && excluded_docs.last().is_some_and(|d| d.file == file_str) {
+                    progress.advance(&file_str, "excluded");
+                    continue;
+                }
+
                 if items.is_empty() {
-                    warnings.push(FileValidationError {
-                        file: file.to_string_lossy().to_string(),
-                        error: "该文件未包含有效数据或格式不匹配".to_string(),
-                    });
+                    file_findings.push(finding(
+                        "empty_file",
+                        &file_str,
+                        "该文件未包含有效数据或格式不匹配".to_string(),
+                    ));
                 } else {
-                    let mut file_has_error = false;
-                    
+                    // 应用源数据修正
+                    if parsed.fingerprint.is_empty() {
+                        file_findings.push(finding(
+                            "parse_failure",
+                            &file_str,
+                            format!("无法读取文件: {:?}", file),
+                        ));
+                    } else {
+                        file_findings.extend(apply_corrections(&mut items, &parsed.fingerprint, corrections));
+                    }
+
                     // 1. 尝试从文件名提取日期
                     let file_name = file.file_name().unwrap_or_default().to_string_lossy();
                     let file_date = extract_date_from_filename(&file_name);
-                    
+
                     for item in &items {
                         // 验证日期格式
                         if let Err(e) = validate_date_str(&item.date) {
-                            errors.push(FileValidationError {
-                                file: file.to_string_lossy().to_string(),
-                                error: format!("日期错误 '{}': {}", item.date, e),
-                            });
-                            file_has_error = true;
+                            file_findings.push(item_finding(
+                                "date_parse",
+                                item,
+                                format!("日期错误 '{}': {}", item.date, e),
+                            ));
                         } else {
                             // 2. 验证文件名日期与内容日期是否一致
                             if let Some(f_date) = file_date {
                                 if let Ok(c_date) = parse_date(&item.date) {
                                     if f_date != c_date {
-                                         warnings.push(FileValidationError {
-                                            file: file.to_string_lossy().to_string(),
-                                            error: format!("日期不一致: 文件名日期 ({}) 与内容日期 ({}) 不同", f_date, c_date),
-                                        });
+                                        file_findings.push(finding(
+                                            "filename_date",
+                                            &file_str,
+                                            format!("日期不一致: 文件名日期 ({}) 与内容日期 ({}) 不同", f_date, c_date),
+                                        ));
                                     }
                                 }
                             }
@@ -144,39 +287,174 @@ pub fn validate_delivery_data(
                         if !item.delivery_order_no.is_empty() {
                             let order_key = (item.customer.clone(), item.delivery_order_no.clone());
                             if let Some(existing_file) = order_no_map.get(&order_key) {
-                                let current_file = file.to_string_lossy().to_string();
-                                if *existing_file != current_file {
-                                    warnings.push(FileValidationError {
-                                        file: current_file.clone(),
-                                        error: format!("送货单号重复: 客户 '{}' 的单号 '{}' 已在文件 '{}' 中存在", 
-                                            item.customer, order_key.1, existing_file.split(|c| c == '/' || c == '\\').last().unwrap_or(existing_file)),
-                                    });
+                                if *existing_file != file_str {
+                                    file_findings.push(finding(
+                                        "duplicate_order_no",
+                                        &file_str,
+                                        format!("送货单号重复: 客户 '{}' 的单号 '{}' 已在文件 '{}' 中存在",
+                                            item.customer, order_key.1, existing_file.split(['/', '\\']).next_back().unwrap_or(existing_file)),
+                                    ));
                                 }
                             } else {
-                                order_no_map.insert(order_key, file.to_string_lossy().to_string());
+                                order_no_map.insert(order_key, file_str.clone());
                             }
                         }
                     }
 
-                    if !file_has_error {
-                        all_items.extend(items);
+                    // 4. 可配置的字段规则
+                    file_findings.extend(check_items(&items, validation, &config.pricing_rules));
+
+                    // 价目表补价、零单价检查、计价换算 (在金额核对之后，补价和换算会重算金额)
+                    file_findings.extend(apply_pricing(&mut items, config));
+
+                    // 5. 按行接受/拒绝
+                    let file_findings = apply_rule_settings(file_findings, validation);
+                    let file_level_error = file_findings
+                        .iter()
+                        .any(|f| f.severity == Severity::Error && f.row.is_none());
+
+                    let mut quarantined_rows = Vec::new();
+                    for item in items {
+                        let row_errors: Vec<&FileValidationError> = file_findings
+                            .iter()
+                            .filter(|f| f.severity == Severity::Error)
+                            .filter(|f| file_level_error || f.row == Some(item.source_row))
+                            .collect();
+
+                        // 只有日期无法识别的行放入待指定日期队列，不归入"未知"月份
+                        if parse_date(&item.date).is_err()
+                            && row_errors.iter().all(|f| f.rule_id == "date_parse")
+                        {
+                            quarantined_rows.push(item.source_row);
+                            unassigned.push(item);
+                        } else if row_errors.is_empty() {
+                            all_items.push(item);
+                        } else {
+                            rejected_rows.push(RejectedRow {
+                                file: file_str.clone(),
+                                row: item.source_row,
+                                reasons: row_errors.iter().map(|f| f.error.clone()).collect(),
+                                item,
+                            });
+                        }
                     }
+
+                    // 待指定日期的行在队列中单独列出，不再作为错误
+                    findings.extend(file_findings.into_iter().filter(|f| {
+                        !(f.rule_id == "date_parse" && f.row.is_some_and(|r| quarantined_rows.contains(&r)))
+                    }));
+                    let status = if rejected_rows.len() > rejected_before { "rejected" } else { "ok" };
+                    progress.advance(&file_str, status);
+                    continue;
                 }
             }
             Err(e) => {
-                errors.push(FileValidationError {
-                    file: file.to_string_lossy().to_string(),
-                    error: format!("解析失败: {}", e),
+                file_findings.push(finding("parse_failure", &file_str, format!("解析失败: {}", e)));
+            }
+        }
+
+        findings.extend(apply_rule_settings(file_findings, validation));
+        progress.advance(&file_str, "error");
+    }
+
+    // 无效的作废清单模式和单号格式提示用户修改设置
+    let (order_formats, invalid_formats) = compile_order_formats(&config.order_no_books);
+    let mut invalid_patterns = void_list.invalid_patterns();
+    invalid_patterns.extend(invalid_formats);
+    findings.extend(apply_rule_settings(invalid_patterns, validation));
+
+    // 去重 (因为循环中可能多次添加相同的结果)
+    findings.sort_by(|a, b| a.file.cmp(&b.file).then(a.row.cmp(&b.row)).then(a.error.cmp(&b.error)));
+    findings.dedup_by(|a, b| a.file == b.file && a.row == b.row && a.error == b.error);
+
+    // 缺号检查使用解析到的全部单号 (包括作废、被拒绝、待指定日期和去重前的行)，避免把这些单号误报为缺号
+    let sequence_items: Vec<DeliveryItem> = all_items
+        .iter()
+        .chain(rejected_rows.iter().map(|r| &r.item))
+        .chain(unassigned.iter())
+        .chain(voided_items.iter())
+        .cloned()
+        .collect();
+    let sequence_issues = check_order_sequences(&sequence_items, &order_formats);
+
+    let (all_items, duplicates) =
+        resolve_duplicates(all_items, &config.duplicate_policy, &config.duplicate_resolutions);
+    let price_anomalies = detect_price_anomalies(&all_items, config.price_anomaly_ratio);
+
+    // 同一送货单的多行只保留一条排除记录
+    excluded_docs.dedup_by(|a, b| a.file == b.file && a.delivery_order_no == b.delivery_order_no);
+
+    ValidationReport {
+        items: all_items,
+        findings,
+        rejected_rows,
+        excluded: excluded_docs,
+        sequence_issues,
+        duplicates,
+        price_anomalies,
+        unassigned,
+        cancelled,
+    }
+}
+
+/// 将每条单价与该客户同一产品/规格之前的近期单价比较，找出偏差过大的记录 (例如多打一个 0 或漏了小数点)
+pub fn detect_price_anomalies(items: &[DeliveryItem], ratio: f64) -> Vec<PriceAnomaly> {
+    let mut anomalies = Vec::new();
+    if ratio <= 1.0 {
+        return anomalies;
+    }
+
+    // (客户, 货名, 规格) -> [(日期, 条目)]
+    type PriceKey<'a> = (&'a str, &'a str, &'a str);
+    let mut history: HashMap<PriceKey, Vec<(chrono::NaiveDate, &DeliveryItem)>> = HashMap::new();
+    for item in items {
+        // 非正数和非有限值 (NaN、无穷大) 的单价不参与比较
+        if !item.unit_price.is_finite() || item.unit_price <= 0.0 {
+            continue;
+        }
+        if let Ok(date) = parse_date(&item.date) {
+            history
+                .entry((&item.customer, &item.product_name, &item.spec))
+                .or_default()
+                .push((date, item));
+        }
+    }
+
+    for lines in history.values_mut() {
+        lines.sort_by_key(|(date, _)| *date);
+
+        for idx in PRICE_HISTORY_MIN..lines.len() {
+            let item = lines[idx].1;
+            let window_start = idx.saturating_sub(PRICE_HISTORY_WINDOW);
+            let mut prices: Vec<f64> = lines[window_start..idx]
+                .iter()
+                .map(|(_, h)| h.unit_price)
+                .collect();
+            prices.sort_by(f64::total_cmp);
+
+            let n = prices.len();
+            let median = (prices[(n - 1) / 2] + prices[n / 2]) / 2.0;
+
+            if item.unit_price > median * ratio || item.unit_price < median / ratio {
+                anomalies.push(PriceAnomaly {
+                    file: item.source_file.clone(),
+                    customer: item.customer.clone(),
+                    product_name: item.product_name.clone(),
+                    spec: item.spec.clone(),
+                    date: item.date.clone(),
+                    delivery_order_no: item.delivery_order_no.clone(),
+                    unit_price: item.unit_price,
+                    history_median: median,
+                    history_min: prices[0],
+                    history_max: prices[prices.len() - 1],
+                    history_count: prices.len(),
                 });
             }
         }
     }
-    
-    // 去重 warnings (因为循环中可能多次添加相同的警告)
-    warnings.sort_by(|a, b| a.file.cmp(&b.file).then(a.error.cmp(&b.error)));
-    warnings.dedup_by(|a, b| a.file == b.file && a.error == b.error);
 
-    (all_items, errors, warnings)
+    anomalies.sort_by(|a, b| a.file.cmp(&b.file).then(a.date.cmp(&b.date)));
+    anomalies
 }
 
 fn extract_date_from_filename(filename: &str) -> Option<chrono::NaiveDate> {
@@ -195,7 +473,7 @@ fn extract_date_from_filename(filename: &str) -> Option<chrono::NaiveDate> {
     None
 }
 
-fn parse_date(date_str: &str) -> Result<chrono::NaiveDate, String> {
+pub fn parse_date(date_str: &str) -> Result<chrono::NaiveDate, String> {
     let formats = ["%Y-%m-%d", "%Y/%m/%d", "%Y年%m月%d日", "%d/%m/%Y", "%m/%d/%Y"];
     for format in &formats {
         if let Ok(date) = chrono::NaiveDate::parse_from_str(date_str, format) {
@@ -210,7 +488,7 @@ fn validate_date_str(date_str: &str) -> Result<(), String> {
     let formats = ["%Y-%m-%d", "%Y/%m/%d", "%Y年%m月%d日", "%d/%m/%Y", "%m/%d/%Y"];
 
     for format in &formats {
-        if let Ok(_) = chrono::NaiveDate::parse_from_str(date_str, format) {
+        if chrono::NaiveDate::parse_from_str(date_str, format).is_ok() {
             return Ok(());
         }
     }
@@ -233,6 +511,9 @@ pub fn generate_summary(items: &[DeliveryItem]) -> Vec<SummaryItem> {
             .entry(key.clone())
             .and_modify(|summary| {
                 summary.quantity += item.quantity;
+                if item.is_free {
+                    summary.free_quantity += item.quantity;
+                }
                 summary.amount += item.amount;
                 // 添加客户到列表（去重）
                 if !item.customer.is_empty() {
@@ -252,68 +533,276 @@ pub fn generate_summary(items: &[DeliveryItem]) -> Vec<SummaryItem> {
             .or_insert_with(|| SummaryItem {
                 product_name: key.0.clone(),
                 spec: key.1.clone(),
+                gauge: item
+                    .spec_info
+                    .as_ref()
+                    .and_then(gauge_label)
+                    .unwrap_or_default(),
                 unit: key.2.clone(),
                 quantity: item.quantity,
+                free_quantity: if item.is_free { item.quantity } else { 0.0 },
                 average_price: 0.0,
                 amount: item.amount,
                 customers: item.customer.clone(),
             });
     }
 
-    // 计算平均单价
+    // 计算平均单价 (赠品不计入)
     let mut summary_vec: Vec<SummaryItem> = summary_map.into_values().collect();
     for item in &mut summary_vec {
-        if item.quantity > 0.0 {
-            item.average_price = item.amount / item.quantity;
+        let paid_quantity = item.quantity - item.free_quantity;
+        if paid_quantity > 0.0 {
+            item.average_price = item.amount / paid_quantity;
             item.average_price = (item.average_price * 100.0).round() / 100.0;
         }
     }
 
     // 按金额降序排列
-    summary_vec.sort_by(|a, b| b.amount.partial_cmp(&a.amount).unwrap());
+    summary_vec.sort_by(|a, b| b.amount.total_cmp(&a.amount));
 
     summary_vec
 }
 
-/// 按客户和月份分组
-pub fn group_by_customer_month(items: &[DeliveryItem]) -> HashMap<(String, String), Vec<DeliveryItem>> {
+/// 按客户和对账单分组 (分组方式按客户设置，默认按月/账期)
+pub fn group_by_customer_statement(
+    items: &[DeliveryItem],
+    config: &AppConfig,
+) -> HashMap<(String, String), Vec<DeliveryItem>> {
     let mut groups: HashMap<(String, String), Vec<DeliveryItem>> = HashMap::new();
 
     for item in items {
-        // 提取年月
-        let year_month = extract_year_month(&item.date);
+        let group = statement_group_key(item, config);
 
-        let key = (item.customer.clone(), year_month);
-        groups.entry(key).or_insert_with(Vec::new).push(item.clone());
+        let key = (item.customer.clone(), group);
+        groups.entry(key).or_default().push(item.clone());
     }
 
     groups
 }
 
-/// 从日期字符串中提取年月
-fn extract_year_month(date_str: &str) -> String {
-    // 尝试多种日期格式
-    let formats = [
-        "%Y-%m-%d",
-        "%Y/%m/%d",
-        "%Y年%m月%d日",
-        "%Y-%m-%d %H:%M:%S",
-        "%d/%m/%Y",
-        "%m/%d/%Y",
-    ];
+/// 按集团汇总子公司的对账单分组: (集团, 分组) -> 各子公司条目
+/// 子公司的分组方式和账期沿用各自的设置
+pub fn group_by_parent_statement(
+    grouped: &HashMap<(String, String), Vec<DeliveryItem>>,
+    config: &AppConfig,
+) -> HashMap<(String, String), Vec<DeliveryItem>> {
+    let mut groups: HashMap<(String, String), Vec<DeliveryItem>> = HashMap::new();
 
-    for format in &formats {
-        if let Ok(date) = chrono::NaiveDate::parse_from_str(date_str, format) {
-            return format!("{}-{:02}", date.year(), date.month());
+    for ((customer, group), items) in grouped {
+        for parent in config.customer_groups.iter().filter(|g| g.children.contains(customer)) {
+            groups
+                .entry((parent.parent.clone(), group.clone()))
+                .or_default()
+                .extend(items.iter().cloned());
         }
     }
 
-    // 如果无法解析，尝试从字符串中提取年月
-    if let Some(pos) = date_str.find('-') {
-        if let Some(second_pos) = date_str[pos + 1..].find('-') {
-            return date_str[0..pos + 1 + second_pos].to_string();
+    groups
+}
+
+/// 计算条目所属对账单分组
+/// 按月: "2024-01" (或账期 "2024-01_2023-12-26_2024-01-25")；按季度: "2024-Q1"；按年: "2024"；
+/// 按订单号: "PO_<订单号>"；按分店: "<月份>@<分店>"
+/// 季度和年度按账期推算: 跨月的账期整体归入其结束日所在的季度/年
+pub fn statement_group_key(item: &DeliveryItem, config: &AppConfig) -> String {
+    let strategy = config
+        .groupings
+        .iter()
+        .find(|g| g.customer == item.customer)
+        .map(|g| g.strategy.clone())
+        .unwrap_or_default();
+
+    let period = || billing_period_key(&item.customer, &item.date, &config.billing_cycles);
+
+    match strategy {
+        GroupingStrategy::Month => period(),
+        GroupingStrategy::Quarter => {
+            let key = period();
+            match period_year_month(&key) {
+                Some((year, month)) => format!("{}-Q{}", year, (month - 1) / 3 + 1),
+                None => key,
+            }
+        }
+        GroupingStrategy::Year => {
+            let key = period();
+            match period_year_month(&key) {
+                Some((year, _)) => year.to_string(),
+                None => key,
+            }
         }
+        GroupingStrategy::OrderNo => format!("PO_{}", sanitize_file_part(&item.order_no)),
+        GroupingStrategy::Branch => format!("{}@{}", period(), sanitize_file_part(&item.branch)),
     }
+}
+
+/// 账期键中的年月 ("2024-01" 或 "2024-01_2023-12-26_2024-01-25")，待指定日期的分组为 None
+fn period_year_month(key: &str) -> Option<(i32, u32)> {
+    let (year, month) = key.get(..7)?.split_once('-')?;
+    Some((year.parse().ok()?, month.parse().ok()?))
+}
+
+/// 去掉不能出现在文件名中的字符
+fn sanitize_file_part(s: &str) -> String {
+    s.trim()
+        .chars()
+        .map(|c| if r#"\/:*?"<>|"#.contains(c) { '-' } else { c })
+        .collect()
+}
+
+/// 计算条目所属账期
+/// 自然月为 "2024-01"，按结账日或自定义账期为 "2024-01_2023-12-26_2024-01-25" (年月取账期结束日所在月)
+pub fn billing_period_key(customer: &str, date_str: &str, cycles: &[BillingCycle]) -> String {
+    let Some(cycle) = cycles.iter().find(|c| c.customer == customer) else {
+        return extract_year_month(date_str);
+    };
+    let Ok(date) = parse_date(date_str) else {
+        return extract_year_month(date_str);
+    };
+
+    // 1. 自定义账期
+    for period in &cycle.periods {
+        if let (Ok(start), Ok(end)) = (parse_date(&period.start), parse_date(&period.end)) {
+            if start <= date && date <= end {
+                return period_key(start, end);
+            }
+        }
+    }
+
+    // 2. 按结账日 (当月没有这一天时以月末结账，例如结账日 30 日的 2 月账期到 2 月末)
+    if (1..=31).contains(&cycle.cutoff_day) {
+        let cutoff = cycle.cutoff_day;
+        let (mut year, mut month) = (date.year(), date.month());
+        if cutoff_date(year, month, cutoff).is_some_and(|end| date > end) {
+            (year, month) = next_month(year, month);
+        }
+        let (prev_year, prev_month) = prev_month(year, month);
+        let start = cutoff_date(prev_year, prev_month, cutoff).and_then(|d| d.succ_opt());
+        if let (Some(start), Some(end)) = (start, cutoff_date(year, month, cutoff)) {
+            return period_key(start, end);
+        }
+    }
+
+    extract_year_month(date_str)
+}
+
+/// 某月的结账日期，当月没有结账日这一天时取月末
+fn cutoff_date(year: i32, month: u32, cutoff: u32) -> Option<chrono::NaiveDate> {
+    let (next_year, next_month) = next_month(year, month);
+    let month_end = chrono::NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()?;
+    month_end.with_day(cutoff.min(month_end.day()))
+}
 
-    "未知".to_string()
+fn period_key(start: chrono::NaiveDate, end: chrono::NaiveDate) -> String {
+    format!("{}-{:02}_{}_{}", end.year(), end.month(), start, end)
+}
+
+fn next_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    }
+}
+
+fn prev_month(year: i32, month: u32) -> (i32, u32) {
+    if month == 1 {
+        (year - 1, 12)
+    } else {
+        (year, month - 1)
+    }
+}
+
+/// 从日期字符串中提取年月
+fn extract_year_month(date_str: &str) -> String {
+    // 尝试多种日期格式 (包括带时间的日期)
+    let date = parse_date(date_str).or_else(|_| {
+        chrono::NaiveDateTime::parse_from_str(date_str, "%Y-%m-%d %H:%M:%S").map(|dt| dt.date())
+    });
+
+    match date {
+        Ok(date) => format!("{}-{:02}", date.year(), date.month()),
+        // 无法解析时不再猜测月份，归入待指定日期分组
+        Err(_) => UNASSIGNED_GROUP.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BillingPeriod, CustomerGrouping};
+
+    fn cycle(cutoff_day: u32) -> Vec<BillingCycle> {
+        vec![BillingCycle {
+            customer: "客户甲".to_string(),
+            cutoff_day,
+            periods: Vec::new(),
+        }]
+    }
+
+    #[test]
+    fn customers_without_cycle_use_calendar_months() {
+        assert_eq!(billing_period_key("客户乙", "2024-01-31", &cycle(25)), "2024-01");
+        assert_eq!(billing_period_key("客户甲", "2024/1/5", &[]), "2024-01");
+        assert_eq!(billing_period_key("客户甲", "无日期", &cycle(25)), UNASSIGNED_GROUP);
+    }
+
+    #[test]
+    fn cutoff_day_belongs_to_the_ending_period() {
+        let cycles = cycle(25);
+        assert_eq!(billing_period_key("客户甲", "2024-01-25", &cycles), "2024-01_2023-12-26_2024-01-25");
+        assert_eq!(billing_period_key("客户甲", "2024-01-26", &cycles), "2024-02_2024-01-26_2024-02-25");
+        assert_eq!(billing_period_key("客户甲", "2024-02-01", &cycles), "2024-02_2024-01-26_2024-02-25");
+    }
+
+    #[test]
+    fn cycle_spanning_year_end_goes_to_january() {
+        let cycles = cycle(25);
+        assert_eq!(billing_period_key("客户甲", "2023-12-26", &cycles), "2024-01_2023-12-26_2024-01-25");
+        assert_eq!(billing_period_key("客户甲", "2023-12-25", &cycles), "2023-12_2023-11-26_2023-12-25");
+    }
+
+    #[test]
+    fn cutoff_after_28_ends_short_months_at_month_end() {
+        let cycles = cycle(30);
+        assert_eq!(billing_period_key("客户甲", "2024-02-29", &cycles), "2024-02_2024-01-31_2024-02-29");
+        assert_eq!(billing_period_key("客户甲", "2024-03-01", &cycles), "2024-03_2024-03-01_2024-03-30");
+        assert_eq!(billing_period_key("客户甲", "2024-03-31", &cycles), "2024-04_2024-03-31_2024-04-30");
+        assert_eq!(billing_period_key("客户甲", "2023-02-28", &cycle(31)), "2023-02_2023-02-01_2023-02-28");
+    }
+
+    #[test]
+    fn custom_periods_take_precedence_over_cutoff() {
+        let mut cycles = cycle(25);
+        cycles[0].periods.push(BillingPeriod {
+            start: "2024-01-20".to_string(),
+            end: "2024-02-10".to_string(),
+        });
+        assert_eq!(billing_period_key("客户甲", "2024-01-22", &cycles), "2024-02_2024-01-20_2024-02-10");
+        assert_eq!(billing_period_key("客户甲", "2024-01-19", &cycles), "2024-01_2023-12-26_2024-01-25");
+    }
+
+    #[test]
+    fn quarters_and_years_follow_billing_periods() {
+        let mut config = AppConfig {
+            billing_cycles: cycle(25),
+            ..Default::default()
+        };
+        let item = DeliveryItem {
+            customer: "客户甲".to_string(),
+            date: "2023-12-27".to_string(),
+            ..Default::default()
+        };
+
+        config.groupings = vec![CustomerGrouping {
+            customer: "客户甲".to_string(),
+            strategy: GroupingStrategy::Quarter,
+        }];
+        assert_eq!(statement_group_key(&item, &config), "2024-Q1");
+
+        config.groupings[0].strategy = GroupingStrategy::Year;
+        assert_eq!(statement_group_key(&item, &config), "2024");
+
+        config.billing_cycles.clear();
+        assert_eq!(statement_group_key(&item, &config), "2023");
+    }
 }
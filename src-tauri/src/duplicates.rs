@@ -0,0 +1,218 @@
+use crate::data_processor::parse_date;
+use crate::models::{DeliveryItem, DuplicateEntry, DuplicateGroup, DuplicatePolicy, DuplicateResolution};
+use std::collections::{BTreeMap, HashSet};
+use std::time::SystemTime;
+
+/// 按内容查找跨文件的重复行 (客户、日期、送货单号、货名规格、数量、金额都相同)
+/// 同一文件内的相同行视为正常的多行，不算重复
+pub fn find_duplicate_groups(items: &[DeliveryItem]) -> Vec<DuplicateGroup> {
+    let mut by_key: BTreeMap<String, Vec<&DeliveryItem>> = BTreeMap::new();
+    for item in items {
+        by_key.entry(content_key(item)).or_default().push(item);
+    }
+
+    by_key
+        .into_iter()
+        .filter(|(_, lines)| {
+            let files: HashSet<&str> = lines.iter().map(|i| i.source_file.as_str()).collect();
+            files.len() > 1
+        })
+        .map(|(key, lines)| {
+            let first = lines[0];
+            DuplicateGroup {
+                customer: first.customer.clone(),
+                date: first.date.clone(),
+                product_name: first.product_name.clone(),
+                spec: first.spec.clone(),
+                quantity: first.quantity,
+                amount: first.amount,
+                entries: lines
+                    .iter()
+                    .map(|i| DuplicateEntry {
+                        file: i.source_file.clone(),
+                        row: i.source_row,
+                        delivery_order_no: i.delivery_order_no.clone(),
+                        modified: file_modified(&i.source_file)
+                            .map(|t| {
+                                chrono::DateTime::<chrono::Local>::from(t)
+                                    .format("%Y-%m-%d %H:%M:%S")
+                                    .to_string()
+                            })
+                            .unwrap_or_default(),
+                        kept: true,
+                    })
+                    .collect(),
+                key,
+                resolved: false,
+            }
+        })
+        .collect()
+}
+
+/// 按用户的处理选择和重复处理策略去掉重复行，返回保留的条目和重复分组 (分组中标明保留了哪些行)
+pub fn resolve_duplicates(
+    items: Vec<DeliveryItem>,
+    policy: &DuplicatePolicy,
+    resolutions: &[DuplicateResolution],
+) -> (Vec<DeliveryItem>, Vec<DuplicateGroup>) {
+    let mut groups = find_duplicate_groups(&items);
+    let by_policy = matches!(policy, DuplicatePolicy::KeepFirst | DuplicatePolicy::KeepNewest);
+
+    // 每组只保留一个文件中的行: 用户指定的文件优先，否则按文件修改时间取最早或最新的文件
+    let mut dropped: HashSet<(String, usize)> = HashSet::new();
+    for group in &mut groups {
+        let resolution = resolutions
+            .iter()
+            .find(|r| r.key == group.key && group.entries.iter().any(|e| e.file == r.kept_file));
+        if let Some(resolution) = resolution {
+            group.resolved = true;
+            for entry in &mut group.entries {
+                entry.kept = entry.file == resolution.kept_file;
+                if !entry.kept {
+                    dropped.insert((entry.file.clone(), entry.row));
+                }
+            }
+            continue;
+        }
+        if !by_policy {
+            continue;
+        }
+
+        let mut files: Vec<(Option<SystemTime>, &str)> = group
+            .entries
+            .iter()
+            .map(|e| (file_modified(&e.file), e.file.as_str()))
+            .collect();
+        files.sort();
+        let kept_file = match policy {
+            DuplicatePolicy::KeepNewest => files.last(),
+            _ => files.first(),
+        }
+        .map(|(_, f)| f.to_string())
+        .unwrap_or_default();
+
+        for entry in &mut group.entries {
+            entry.kept = entry.file == kept_file;
+            if !entry.kept {
+                dropped.insert((entry.file.clone(), entry.row));
+            }
+        }
+    }
+
+    let items = items
+        .into_iter()
+        .filter(|i| !dropped.contains(&(i.source_file.clone(), i.source_row)))
+        .collect();
+
+    (items, groups)
+}
+
+/// 条目是否属于某个未处理的重复分组 (用于阻止生成包含重复行的对账单)
+pub fn is_duplicate_line(item: &DeliveryItem, groups: &[DuplicateGroup]) -> bool {
+    groups.iter().filter(|g| !g.resolved).any(|g| {
+        g.entries
+            .iter()
+            .any(|e| e.file == item.source_file && e.row == item.source_row)
+    })
+}
+
+fn content_key(item: &DeliveryItem) -> String {
+    let date = parse_date(&item.date)
+        .map(|d| d.to_string())
+        .unwrap_or_else(|_| item.date.clone());
+    // 送货单号不同的两张单即使内容相同也是两次送货，不算重复
+    format!(
+        "{}|{}|{}|{}|{}|{:.4}|{:.2}",
+        item.customer, date, item.delivery_order_no, item.product_name, item.spec, item.quantity, item.amount
+    )
+}
+
+fn file_modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(file: &str, row: usize, delivery_order_no: &str) -> DeliveryItem {
+        DeliveryItem {
+            product_name: "PE膜".to_string(),
+            spec: "160*1000米".to_string(),
+            quantity: 10.0,
+            unit: "卷".to_string(),
+            unit_price: 12.5,
+            amount: 125.0,
+            customer: "客户甲".to_string(),
+            date: "2024-01-05".to_string(),
+            delivery_order_no: delivery_order_no.to_string(),
+            source_file: file.to_string(),
+            source_row: row,
+            billing_quantity: 10.0,
+            billing_unit: "卷".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn content_key_normalizes_dates_and_includes_order_no() {
+        let a = item("a.xlsx", 9, "A001");
+        let mut b = item("b.xlsx", 12, "A001");
+        b.date = "2024/1/5".to_string();
+        assert_eq!(content_key(&a), content_key(&b));
+
+        let c = item("b.xlsx", 12, "A002");
+        assert_ne!(content_key(&a), content_key(&c));
+    }
+
+    #[test]
+    fn finds_lines_repeated_across_files() {
+        let items = [item("a.xlsx", 9, "A001"), item("b.xlsx", 12, "A001")];
+        let groups = find_duplicate_groups(&items);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].key, content_key(&items[0]));
+        assert_eq!(groups[0].entries.len(), 2);
+        assert!(groups[0].entries.iter().all(|e| e.kept));
+    }
+
+    #[test]
+    fn ignores_repeats_within_a_file_and_different_orders() {
+        let same_file = [item("a.xlsx", 9, "A001"), item("a.xlsx", 10, "A001")];
+        assert!(find_duplicate_groups(&same_file).is_empty());
+
+        let other_order = [item("a.xlsx", 9, "A001"), item("b.xlsx", 9, "A002")];
+        assert!(find_duplicate_groups(&other_order).is_empty());
+    }
+
+    #[test]
+    fn warn_policy_keeps_all_lines() {
+        let items = vec![item("a.xlsx", 9, "A001"), item("b.xlsx", 12, "A001")];
+        let (kept, groups) = resolve_duplicates(items, &DuplicatePolicy::Warn, &[]);
+        assert_eq!(kept.len(), 2);
+        assert!(is_duplicate_line(&kept[0], &groups));
+    }
+
+    #[test]
+    fn keep_first_drops_other_files() {
+        // 文件不存在时修改时间相同，按文件名排序
+        let items = vec![item("b.xlsx", 12, "A001"), item("a.xlsx", 9, "A001")];
+        let (kept, groups) = resolve_duplicates(items, &DuplicatePolicy::KeepFirst, &[]);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].source_file, "a.xlsx");
+        assert!(groups[0].entries.iter().any(|e| e.file == "b.xlsx" && !e.kept));
+    }
+
+    #[test]
+    fn user_resolution_overrides_policy() {
+        let items = vec![item("a.xlsx", 9, "A001"), item("b.xlsx", 12, "A001")];
+        let resolutions = [DuplicateResolution {
+            key: content_key(&items[0]),
+            kept_file: "b.xlsx".to_string(),
+        }];
+        let (kept, groups) = resolve_duplicates(items, &DuplicatePolicy::KeepFirst, &resolutions);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].source_file, "b.xlsx");
+        assert!(groups[0].resolved);
+        assert!(!is_duplicate_line(&kept[0], &groups));
+    }
+}
@@ -0,0 +1,270 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+pub type AppResult<T> = std::result::Result<T, AppError>;
+
+/// 文件操作类型 (用于错误信息)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoAction {
+    ScanDir,
+    CreateDir,
+    ReadFile,
+    WriteFile,
+}
+
+impl IoAction {
+    fn key(self) -> &'static str {
+        match self {
+            IoAction::ScanDir => "scan_dir",
+            IoAction::CreateDir => "create_dir",
+            IoAction::ReadFile => "read_file",
+            IoAction::WriteFile => "write_file",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            IoAction::ScanDir => "扫描目录失败",
+            IoAction::CreateDir => "创建目录失败",
+            IoAction::ReadFile => "读取文件失败",
+            IoAction::WriteFile => "写入文件失败",
+        }
+    }
+}
+
+/// 配置文件操作类型 (用于错误信息)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigAction {
+    SaveConfig,
+    SaveCorrections,
+}
+
+impl ConfigAction {
+    fn key(self) -> &'static str {
+        match self {
+            ConfigAction::SaveConfig => "save_config",
+            ConfigAction::SaveCorrections => "save_corrections",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ConfigAction::SaveConfig => "保存配置失败",
+            ConfigAction::SaveCorrections => "保存源数据修正失败",
+        }
+    }
+}
+
+/// 后端错误: 序列化为 { code, message_key, params, message, file, sheet, cell }
+/// code 和 message_key 保持稳定，界面据此判断错误类型；message 为中文说明
+#[derive(Debug, Clone)]
+pub enum AppError {
+    /// 未找到任何 Excel 文件
+    NoExcelFiles,
+    /// 没有可生成的数据
+    NoData,
+    /// 扫描会话已过期
+    SessionExpired,
+    /// 所选客户和分组没有送货记录
+    EmptyStatement { customer: String, period: String },
+    /// 包含日期无法识别的送货记录
+    UnassignedDates { customer: String, period: String },
+    /// 包含跨文件重复的送货记录
+    DuplicateLines { customer: String, period: String },
+    /// 文件或目录操作失败
+    Io { action: IoAction, path: String, reason: String },
+    /// 配置或源数据修正文件读写失败
+    Config { action: ConfigAction, reason: String },
+    /// 台账数据库读写失败
+    Ledger { reason: String },
+    /// 监视原始数据目录失败
+    Watcher { path: String, reason: String },
+    /// 送货单工作簿读取失败
+    Workbook {
+        file: String,
+        sheet: Option<String>,
+        cell: Option<String>,
+        reason: String,
+    },
+    /// 对账单写入失败
+    Statement { file: Option<String>, reason: String },
+}
+
+impl AppError {
+    pub fn io(action: IoAction, path: &Path, reason: impl fmt::Display) -> Self {
+        AppError::Io {
+            action,
+            path: path.to_string_lossy().to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn config(action: ConfigAction, reason: impl fmt::Display) -> Self {
+        AppError::Config {
+            action,
+            reason: format!("{:#}", reason),
+        }
+    }
+
+    pub fn ledger(reason: impl fmt::Display) -> Self {
+        AppError::Ledger {
+            reason: format!("{:#}", reason),
+        }
+    }
+
+    pub fn workbook(file: &Path, reason: impl fmt::Display) -> Self {
+        AppError::Workbook {
+            file: file.to_string_lossy().to_string(),
+            sheet: None,
+            cell: None,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// 为对账单写入错误补充文件路径
+    pub fn with_file(self, file: &Path) -> Self {
+        match self {
+            AppError::Statement { file: None, reason } => AppError::Statement {
+                file: Some(file.to_string_lossy().to_string()),
+                reason,
+            },
+            other => other,
+        }
+    }
+
+    /// 稳定的错误代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NoExcelFiles => "NO_EXCEL_FILES",
+            AppError::NoData => "NO_DATA",
+            AppError::SessionExpired => "SESSION_EXPIRED",
+            AppError::EmptyStatement { .. } => "EMPTY_STATEMENT",
+            AppError::UnassignedDates { .. } => "UNASSIGNED_DATES",
+            AppError::DuplicateLines { .. } => "DUPLICATE_LINES",
+            AppError::Io { .. } => "IO",
+            AppError::Config { .. } => "CONFIG",
+            AppError::Ledger { .. } => "LEDGER",
+            AppError::Watcher { .. } => "WATCHER",
+            AppError::Workbook { .. } => "WORKBOOK",
+            AppError::Statement { .. } => "STATEMENT",
+        }
+    }
+
+    /// 界面翻译用的消息键
+    pub fn message_key(&self) -> String {
+        match self {
+            AppError::Io { action, .. } => format!("error.io.{}", action.key()),
+            AppError::Config { action, .. } => format!("error.config.{}", action.key()),
+            other => format!("error.{}", other.code().to_lowercase()),
+        }
+    }
+
+    /// 消息参数
+    pub fn params(&self) -> BTreeMap<&'static str, String> {
+        let mut params = BTreeMap::new();
+        match self {
+            AppError::EmptyStatement { customer, period }
+            | AppError::UnassignedDates { customer, period }
+            | AppError::DuplicateLines { customer, period } => {
+                params.insert("customer", customer.clone());
+                params.insert("period", period.clone());
+            }
+            AppError::Io { path, reason, .. } | AppError::Watcher { path, reason } => {
+                params.insert("path", path.clone());
+                params.insert("reason", reason.clone());
+            }
+            AppError::Config { reason, .. }
+            | AppError::Ledger { reason }
+            | AppError::Workbook { reason, .. }
+            | AppError::Statement { reason, .. } => {
+                params.insert("reason", reason.clone());
+            }
+            AppError::NoExcelFiles | AppError::NoData | AppError::SessionExpired => {}
+        }
+        params
+    }
+
+    /// 出错的文件
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            AppError::Io { path, .. } => Some(path),
+            AppError::Workbook { file, .. } => Some(file),
+            AppError::Statement { file, .. } => file.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NoExcelFiles => write!(f, "未找到任何 Excel 文件"),
+            AppError::NoData => write!(f, "未提取到任何数据"),
+            AppError::SessionExpired => write!(f, "扫描结果已过期，请重新扫描"),
+            AppError::EmptyStatement { customer, period } => {
+                write!(f, "{} {} 没有送货记录", customer, period)
+            }
+            AppError::UnassignedDates { customer, period } => {
+                write!(f, "{} {} 包含日期无法识别的送货记录，请先指定日期", customer, period)
+            }
+            AppError::DuplicateLines { customer, period } => {
+                write!(f, "{} {} 存在跨文件重复的送货记录，请先处理重复数据", customer, period)
+            }
+            AppError::Io { action, path, reason } => write!(f, "{}: {}: {}", action.label(), path, reason),
+            AppError::Config { action, reason } => write!(f, "{}: {}", action.label(), reason),
+            AppError::Ledger { reason } => write!(f, "台账读写失败: {}", reason),
+            AppError::Watcher { path, reason } => write!(f, "无法监视目录 {}: {}", path, reason),
+            AppError::Workbook {
+                file,
+                sheet,
+                cell,
+                reason,
+            } => {
+                write!(f, "读取文件失败: {}", file)?;
+                if let Some(sheet) = sheet {
+                    write!(f, " 工作表 {}", sheet)?;
+                }
+                if let Some(cell) = cell {
+                    write!(f, " 单元格 {}", cell)?;
+                }
+                write!(f, ": {}", reason)
+            }
+            AppError::Statement { file, reason } => match file {
+                Some(file) => write!(f, "生成对账单失败: {}: {}", file, reason),
+                None => write!(f, "生成对账单失败: {}", reason),
+            },
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (sheet, cell) = match self {
+            AppError::Workbook { sheet, cell, .. } => (sheet.as_deref(), cell.as_deref()),
+            _ => (None, None),
+        };
+
+        let mut state = serializer.serialize_struct("AppError", 7)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message_key", &self.message_key())?;
+        state.serialize_field("params", &self.params())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("file", &self.file())?;
+        state.serialize_field("sheet", &sheet)?;
+        state.serialize_field("cell", &cell)?;
+        state.end()
+    }
+}
+
+impl From<rust_xlsxwriter::XlsxError> for AppError {
+    fn from(e: rust_xlsxwriter::XlsxError) -> Self {
+        AppError::Statement {
+            file: None,
+            reason: e.to_string(),
+        }
+    }
+}
@@ -1,22 +1,28 @@
 use crate::models::DeliveryItem;
-use anyhow::{Context, Result};
+use crate::spec_parser::{looks_like_dimensions, parse_spec};
+use crate::error::{AppError, AppResult};
 use calamine::{open_workbook_auto, Data, Reader};
 use std::path::Path;
 
 /// 从 Excel 文件中提取送货单数据
-pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Vec<DeliveryItem>> {
+pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> AppResult<Vec<DeliveryItem>> {
     let mut workbook = open_workbook_auto(file_path)
-        .with_context(|| format!("无法打开文件: {:?}", file_path))?;
+        .map_err(|e| AppError::workbook(file_path, e))?;
 
     let sheet_name = workbook
         .sheet_names()
         .first()
-        .context("工作簿没有工作表")?
+        .ok_or_else(|| AppError::workbook(file_path, "工作簿没有工作表"))?
         .clone();
 
     let range = workbook
         .worksheet_range(&sheet_name)
-        .context("无法读取工作表")?;
+        .map_err(|e| AppError::Workbook {
+            file: file_path.to_string_lossy().to_string(),
+            sheet: Some(sheet_name.clone()),
+            cell: None,
+            reason: e.to_string(),
+        })?;
 
     let mut items = Vec::new();
 
@@ -24,8 +30,9 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
     let mut date = String::new();
     let mut delivery_order_no = String::new();
     let mut header_order_no = String::new();
+    let mut branch = String::new();
 
-    // 扫描前 10 行以提取 客户、日期、单号、全局订单号
+    // 扫描前 10 行以提取 客户、日期、单号、全局订单号、收货分店
     for row_idx in 0..10 {
         if let Some(row) = range.rows().nth(row_idx) {
             for (col_idx, cell) in row.iter().enumerate() {
@@ -34,7 +41,7 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
 
                 // 1. 提取客户名称
                 if (cell_str.contains("客户") || cell_str.contains("单位")) && customer_name.is_empty() {
-                    let parts: Vec<&str> = cell_str.split(|c| c == ':' || c == '：').collect();
+                    let parts: Vec<&str> = cell_str.split([':', '：']).collect();
                     if parts.len() > 1 && !parts[1].trim().is_empty() {
                         customer_name = parts[1].trim().to_string();
                     } else if let Some(next_cell) = row.get(col_idx + 1) {
@@ -47,7 +54,7 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
 
                 // 2. 提取日期
                 if cell_str.contains("日期") && date.is_empty() {
-                    let parts: Vec<&str> = cell_str.split(|c| c == ':' || c == '：').collect();
+                    let parts: Vec<&str> = cell_str.split([':', '：']).collect();
                     if parts.len() > 1 && !parts[1].trim().is_empty() {
                         date = normalize_date(parts[1].trim());
                     } else if let Some(next_cell) = row.get(col_idx + 1) {
@@ -57,7 +64,7 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
 
                 // 3. 提取送货单号 (No)
                 if (cell_lower.contains("no") || cell_lower.contains("单号")) && delivery_order_no.is_empty() {
-                    let parts: Vec<&str> = cell_str.split(|c| c == ':' || c == '：' || c == '.' || c == ' ').collect();
+                    let parts: Vec<&str> = cell_str.split([':', '：', '.', ' ']).collect();
                     for (p_idx, part) in parts.iter().enumerate() {
                         let p_trimmed = part.trim();
                         let p_lower = p_trimmed.to_lowercase();
@@ -85,9 +92,24 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
                     }
                 }
 
-                // 4. 提取全局订单号 (例如在备注或标题区域)
+                // 4. 提取收货分店 (连锁客户按分店对账)
+                if (cell_str.contains("分店") || cell_str.contains("门店") || cell_str.contains("收货地址") || cell_str.contains("送货地址"))
+                    && branch.is_empty()
+                {
+                    let parts: Vec<&str> = cell_str.split([':', '：']).collect();
+                    if parts.len() > 1 && !parts[1].trim().is_empty() {
+                        branch = parts[1].trim().to_string();
+                    } else if let Some(next_cell) = row.get(col_idx + 1) {
+                        let val = next_cell.to_string().trim().to_string();
+                        if !val.is_empty() {
+                            branch = val;
+                        }
+                    }
+                }
+
+                // 5. 提取全局订单号 (例如在备注或标题区域)
                 if cell_str.contains("订单号") && header_order_no.is_empty() {
-                    let parts: Vec<&str> = cell_str.split(|c| c == ':' || c == '：').collect();
+                    let parts: Vec<&str> = cell_str.split([':', '：']).collect();
                     if parts.len() > 1 && !parts[1].trim().is_empty() {
                         header_order_no = parts[1].trim().to_string();
                     } else if let Some(next_cell) = row.get(col_idx + 1) {
@@ -125,6 +147,10 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
                     col_map.insert("amount", col_idx);
                 } else if s.contains("订单号") || s.contains("PO") {
                     col_map.insert("order_no", col_idx);
+                } else if s.contains("赠品") || s.contains("赠送") {
+                    col_map.insert("free", col_idx);
+                } else if s.contains("备注") || s.contains("Remark") {
+                    col_map.insert("remark", col_idx);
                 }
             }
             if found_header {
@@ -141,6 +167,14 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
     let idx_price = col_map.get("price").cloned();
     let idx_amount = col_map.get("amount").cloned();
     let idx_order_no = col_map.get("order_no").cloned();
+    let idx_free = col_map.get("free").cloned();
+    let idx_remark = col_map.get("remark").cloned();
+
+    // 区域起始行列 (用于换算为 Excel 行号和单元格位置)
+    let (first_row, first_col) = range
+        .start()
+        .map(|(r, c)| (r as usize, c as usize))
+        .unwrap_or((0, 0));
 
     for (idx, row) in range.rows().enumerate() {
         if idx < data_start_row {
@@ -148,7 +182,7 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
         }
 
         // 检查是否到达合计行
-        let first_cell = row.get(0).map(|c| c.to_string()).unwrap_or_default();
+        let first_cell = row.first().map(|c| c.to_string()).unwrap_or_default();
         if first_cell.contains("合计") || first_cell.contains("送货单位") {
             break;
         }
@@ -170,11 +204,28 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
             .map(|c| c.to_string().trim().to_string())
             .unwrap_or_default();
 
-        // 提取数量
-        let quantity = row.get(idx_quantity).and_then(|c| extract_number(c));
+        // 数量、单价、金额单元格是公式错误值时数据不可信，报告出错的单元格
+        for col_idx in [Some(idx_quantity), idx_price, idx_amount].into_iter().flatten() {
+            if let Some(Data::Error(e)) = row.get(col_idx) {
+                return Err(AppError::Workbook {
+                    file: file_path.to_string_lossy().to_string(),
+                    sheet: Some(sheet_name.clone()),
+                    cell: Some(cell_ref(first_row + idx, first_col + col_idx)),
+                    reason: format!("单元格包含错误值 {}", e),
+                });
+            }
+        }
+
+        // 提取数量 (单元格有内容但不是数字时保留该行，由校验规则报告)
+        let quantity_cell = row.get(idx_quantity);
+        let quantity = quantity_cell.and_then(extract_number);
+        let unreadable_quantity = match quantity {
+            Some(_) => String::new(),
+            None => quantity_cell.map(|c| c.to_string().trim().to_string()).unwrap_or_default(),
+        };
 
         // 跳过没有数量的行
-        if quantity.is_none() {
+        if quantity.is_none() && unreadable_quantity.is_empty() {
             continue;
         }
 
@@ -184,17 +235,19 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
             .map(|c| c.to_string().trim().to_string())
             .unwrap_or_default();
 
-        // 提取单价
-        let unit_price = idx_price.and_then(|i| row.get(i)).and_then(|c| extract_number(c)).unwrap_or(0.0);
+        // 提取单价 (区分没有单价和单价为 0)
+        let price_cell = idx_price.and_then(|i| row.get(i)).and_then(extract_number);
+        let price_missing = price_cell.is_none();
+        let unit_price = price_cell.unwrap_or(0.0);
 
         // 提取金额
-        let amount = idx_amount.and_then(|i| row.get(i)).and_then(|c| extract_number(c)).unwrap_or(0.0);
+        let amount = idx_amount.and_then(|i| row.get(i)).and_then(extract_number).unwrap_or(0.0);
 
         // 尝试从当前行的所有单元格中提取“订单号：xxxx” (处理埋在备注里的情况)
         for cell in row.iter() {
             let s = cell.to_string();
             if s.contains("订单号") {
-                let parts: Vec<&str> = s.split(|c| c == ':' || c == '：').collect();
+                let parts: Vec<&str> = s.split([':', '：']).collect();
                 if parts.len() > 1 && !parts[1].trim().is_empty() {
                     let extracted = parts[1].trim().to_string();
                     if header_order_no.is_empty() || header_order_no == extracted {
@@ -211,10 +264,38 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
             .filter(|s| !s.is_empty())
             .unwrap_or_else(|| header_order_no.clone());
 
+        // 提取备注
+        let remark = idx_remark
+            .and_then(|i| row.get(i))
+            .map(|c| c.to_string().trim().to_string())
+            .unwrap_or_default();
+
+        // 识别赠品 (赠品列有标记，或货名/备注中包含赠品关键字)
+        let free_flag = idx_free
+            .and_then(|i| row.get(i))
+            .map(|c| is_truthy_mark(&c.to_string()))
+            .unwrap_or(false);
+        let product_name = product_name.unwrap();
+        let is_free = free_flag || is_free_goods_text(&product_name) || is_free_goods_text(&remark);
+
+        let spec_info = parse_spec(&spec);
+
+        let quantity = quantity.unwrap_or(0.0);
+
         items.push(DeliveryItem {
-            product_name: product_name.unwrap(),
+            product_name,
             spec,
-            quantity: quantity.unwrap(),
+            quantity,
+            billing_quantity: quantity,
+            billing_unit: unit.clone(),
+            price_filled: false,
+            price_missing,
+            unreadable_quantity,
+            is_free,
+            remark,
+            corrections: Vec::new(),
+            branch: branch.clone(),
+            statement_group: String::new(),
             unit,
             unit_price,
             amount,
@@ -223,13 +304,45 @@ pub fn extract_delivery_data(file_path: &Path, customer_type: &str) -> Result<Ve
             delivery_order_no: delivery_order_no.clone(),
             order_no: row_order_no,
             source_file: file_path.to_string_lossy().to_string(),
+            source_row: first_row + idx + 1,
+            sheet_name: sheet_name.clone(),
             customer_type: customer_type.to_string(),
+            spec_info,
         });
     }
 
     Ok(items)
 }
 
+/// 单元格位置 (例如第 0 行第 3 列为 D1)
+fn cell_ref(row: usize, col: usize) -> String {
+    let mut letters = String::new();
+    let mut n = col + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        letters.insert(0, (b'A' + rem as u8) as char);
+        n = (n - 1) / 26;
+    }
+    format!("{}{}", letters, row + 1)
+}
+
+/// 赠品关键字
+const FREE_GOODS_KEYWORDS: [&str; 3] = ["赠品", "赠送", "免费"];
+
+/// 货名或备注中是否标明为赠品
+pub fn is_free_goods_text(text: &str) -> bool {
+    FREE_GOODS_KEYWORDS.iter().any(|k| text.contains(k))
+}
+
+/// 赠品列中的标记 (例如 "是"、"√"、"Y"、"赠品")
+fn is_truthy_mark(text: &str) -> bool {
+    let text = text.trim();
+    matches!(
+        text.to_lowercase().as_str(),
+        "是" | "√" | "✓" | "y" | "yes" | "1" | "true"
+    ) || is_free_goods_text(text)
+}
+
 /// 从单元格提取数字
 fn extract_number(cell: &Data) -> Option<f64> {
     match cell {
@@ -241,7 +354,11 @@ fn extract_number(cell: &Data) -> Option<f64> {
             if let Ok(f) = s.parse::<f64>() {
                 return Some(f);
             }
-            // 尝试提取开头的数字部分 (处理类似 "160*1000米" 的情况)
+            // "160*1000米" 这类是规格而不是数量，不能只取开头的 160
+            if looks_like_dimensions(s) {
+                return None;
+            }
+            // 尝试提取开头的数字部分 (处理类似 "100米" 的情况)
             let mut num_str = String::new();
             for c in s.chars() {
                 if c.is_numeric() || c == '.' || c == '-' {
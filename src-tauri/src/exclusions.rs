@@ -0,0 +1,102 @@
+use crate::models::{ExcludedDocument, FileValidationError, VoidRule, VoidRuleKind};
+use crate::validation::finding;
+use regex::Regex;
+use std::path::Path;
+
+/// 编译好的作废清单 (每次扫描编译一次模式)
+pub struct VoidList<'a> {
+    /// 规则及其模式的编译结果 (仅模式规则有)
+    rules: Vec<(&'a VoidRule, Option<Result<Regex, regex::Error>>)>,
+}
+
+impl<'a> VoidList<'a> {
+    pub fn new(rules: &'a [VoidRule]) -> Self {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let pattern = (rule.kind == VoidRuleKind::Pattern && !rule.value.is_empty())
+                    .then(|| Regex::new(&rule.value));
+                (rule, pattern)
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// 无效的正则模式 (这些模式按普通文字匹配)
+    pub fn invalid_patterns(&self) -> Vec<FileValidationError> {
+        self.rules
+            .iter()
+            .filter_map(|(rule, pattern)| match pattern {
+                Some(Err(e)) => Some(finding(
+                    "invalid_pattern",
+                    "",
+                    format!("作废清单模式 '{}' 不是有效的正则表达式，已按普通文字匹配: {}", rule.value, e),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 按作废清单检查文件 (按文件或文件名模式)，返回排除原因
+    pub fn match_file(&self, path: &Path) -> Option<String> {
+        let path_str = path.to_string_lossy();
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        self.rules.iter().find_map(|(rule, pattern)| {
+            let matched = match rule.kind {
+                VoidRuleKind::File => rule.value == path_str || rule.value == file_name,
+                VoidRuleKind::Pattern => matches_pattern(rule, pattern.as_ref(), &file_name),
+                VoidRuleKind::DeliveryOrderNo => false,
+            };
+            matched.then(|| reason_of(rule, &format!("文件名匹配 '{}'", rule.value)))
+        })
+    }
+
+    /// 按作废清单检查工作表名称，返回排除原因
+    pub fn match_sheet(&self, sheet_name: &str) -> Option<String> {
+        self.rules.iter().find_map(|(rule, pattern)| {
+            (rule.kind == VoidRuleKind::Pattern && matches_pattern(rule, pattern.as_ref(), sheet_name))
+                .then(|| reason_of(rule, &format!("工作表名匹配 '{}'", rule.value)))
+        })
+    }
+
+    /// 按作废清单检查送货单号 (可限定客户)，返回排除原因
+    pub fn match_order(&self, customer: &str, delivery_order_no: &str) -> Option<String> {
+        self.rules.iter().find_map(|(rule, _)| {
+            (rule.kind == VoidRuleKind::DeliveryOrderNo
+                && !delivery_order_no.is_empty()
+                && rule.value == delivery_order_no
+                && (rule.customer.is_empty() || rule.customer == customer))
+                .then(|| reason_of(rule, "送货单号在作废清单中"))
+        })
+    }
+}
+
+/// 创建一条排除记录
+pub fn excluded(file: &str, delivery_order_no: &str, reason: String) -> ExcludedDocument {
+    ExcludedDocument {
+        file: file.to_string(),
+        delivery_order_no: delivery_order_no.to_string(),
+        reason,
+    }
+}
+
+/// 模式按正则匹配，正则无效时按普通文字匹配
+fn matches_pattern(rule: &VoidRule, pattern: Option<&Result<Regex, regex::Error>>, text: &str) -> bool {
+    match pattern {
+        Some(Ok(re)) => re.is_match(text),
+        Some(Err(_)) => text.contains(&rule.value),
+        None => false,
+    }
+}
+
+fn reason_of(rule: &VoidRule, default: &str) -> String {
+    if rule.reason.is_empty() {
+        default.to_string()
+    } else {
+        rule.reason.clone()
+    }
+}
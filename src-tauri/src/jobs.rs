@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// 正在运行的任务 (扫描、校验、生成)，按任务 ID 取消
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    /// 台账写入锁: 任务和自动导入依次读写台账 (只在读写台账时持有，解析和生成期间不持有)
+    ledger: Mutex<()>,
+}
+
+impl JobRegistry {
+    /// 等待其他任务或自动导入的台账读写结束，取得台账写入锁
+    pub fn lock_ledger(&self) -> MutexGuard<'_, ()> {
+        self.ledger.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 登记新任务，返回取消标志
+    pub fn register(&self, job_id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.jobs
+            .lock()
+            .unwrap()
+            .insert(job_id.to_string(), flag.clone());
+        flag
+    }
+
+    /// 请求取消任务，任务会在处理完当前文件后停止；任务不存在时返回 false
+    pub fn cancel(&self, job_id: &str) -> bool {
+        match self.jobs.lock().unwrap().get(job_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 任务结束后移除
+    pub fn finish(&self, job_id: &str) {
+        self.jobs.lock().unwrap().remove(job_id);
+    }
+}
+
+/// 任务的取消标志，任务结束时自动从登记表中移除
+pub struct JobGuard<'a> {
+    registry: &'a JobRegistry,
+    id: String,
+    pub cancelled: Arc<AtomicBool>,
+}
+
+impl<'a> JobGuard<'a> {
+    /// 登记任务；未提供任务 ID 时生成一个不可取消的任务
+    pub fn start(registry: &'a JobRegistry, job_id: Option<String>) -> Self {
+        let id = job_id.unwrap_or_default();
+        let cancelled = if id.is_empty() {
+            Arc::new(AtomicBool::new(false))
+        } else {
+            registry.register(&id)
+        };
+        Self {
+            registry,
+            id,
+            cancelled,
+        }
+    }
+}
+
+impl Drop for JobGuard<'_> {
+    fn drop(&mut self) {
+        if !self.id.is_empty() {
+            self.registry.finish(&self.id);
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use crate::corrections::file_fingerprint;
+use crate::models::DeliveryItem;
+use crate::scan_cache::{self, cached_fingerprint};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const LEDGER_FILE: &str = "ledger.db";
+
+/// 数据库被其他连接锁定时的最长等待时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS source_files (
+    path          TEXT PRIMARY KEY,
+    fingerprint   TEXT NOT NULL DEFAULT '',
+    customer_type TEXT NOT NULL DEFAULT '',
+    first_seen    TEXT NOT NULL,
+    last_seen     TEXT NOT NULL,
+    archived      INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS delivery_notes (
+    source_file       TEXT NOT NULL,
+    delivery_order_no TEXT NOT NULL,
+    customer          TEXT NOT NULL,
+    date              TEXT NOT NULL,
+    order_no          TEXT NOT NULL,
+    PRIMARY KEY (source_file, delivery_order_no)
+);
+CREATE TABLE IF NOT EXISTS delivery_lines (
+    source_file       TEXT NOT NULL,
+    source_row        INTEGER NOT NULL,
+    sheet_name        TEXT NOT NULL,
+    customer          TEXT NOT NULL,
+    date              TEXT NOT NULL,
+    delivery_order_no TEXT NOT NULL,
+    order_no          TEXT NOT NULL,
+    product_name      TEXT NOT NULL,
+    spec              TEXT NOT NULL,
+    quantity          REAL NOT NULL,
+    unit              TEXT NOT NULL,
+    unit_price        REAL NOT NULL,
+    amount            REAL NOT NULL,
+    data              TEXT NOT NULL,
+    updated_at        TEXT NOT NULL,
+    PRIMARY KEY (source_file, source_row)
+);
+CREATE INDEX IF NOT EXISTS idx_lines_customer_date ON delivery_lines (customer, date);
+CREATE TABLE IF NOT EXISTS line_corrections (
+    source_file TEXT NOT NULL,
+    source_row  INTEGER NOT NULL,
+    field       TEXT NOT NULL,
+    original    TEXT NOT NULL,
+    corrected   TEXT NOT NULL,
+    PRIMARY KEY (source_file, source_row, field)
+);
+CREATE TABLE IF NOT EXISTS statements (
+    file_path    TEXT PRIMARY KEY,
+    customer     TEXT NOT NULL,
+    period       TEXT NOT NULL,
+    item_count   INTEGER NOT NULL,
+    total_amount REAL NOT NULL,
+    generated_at TEXT NOT NULL
+);
+";
+
+/// 旧版本数据库缺少的列 (表, 列, 定义)
+const MIGRATIONS: &[(&str, &str, &str)] = &[
+    ("statements", "source_hash", "TEXT NOT NULL DEFAULT ''"),
+    ("statements", "consolidated", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+/// 获取台账数据库路径 (与配置文件同目录)
+fn get_ledger_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("ana")
+        .join(LEDGER_FILE)
+}
+
+/// 启动时初始化台账数据库 (不存在时创建，并升级旧版本的表结构)
+pub fn init_ledger() -> Result<()> {
+    let conn = open_ledger()?;
+    conn.execute_batch(SCHEMA).context("初始化台账数据库失败")?;
+    conn.execute_batch(scan_cache::SCHEMA).context("初始化解析缓存失败")?;
+    migrate(&conn).context("升级台账数据库失败")?;
+    Ok(())
+}
+
+/// 打开台账数据库 (表结构由 `init_ledger` 在启动时创建)
+pub fn open_ledger() -> Result<Connection> {
+    let path = get_ledger_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("创建配置目录失败")?;
+    }
+    let conn = Connection::open(&path).with_context(|| format!("无法打开台账数据库: {:?}", path))?;
+    // 其他连接正在写入时等待，而不是立即报 "database is locked"
+    conn.busy_timeout(BUSY_TIMEOUT).context("设置台账数据库等待时间失败")?;
+    Ok(conn)
+}
+
+/// 为旧版本数据库补充新增的列
+fn migrate(conn: &Connection) -> Result<()> {
+    for (table, column, definition) in MIGRATIONS {
+        let columns: Vec<String> = {
+            let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+            let rows = stmt.query_map([], |row| row.get(1))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        if !columns.iter().any(|c| c == column) {
+            conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))?;
+        }
+    }
+    Ok(())
+}
+
+/// 将本次扫描结果写入台账
+/// 本次扫描到的文件以最新内容替换 (被拒绝或作废的行随之移除)；未扫描到的文件保留历史数据，
+/// 源文件已不存在的标记为已归档
+pub fn sync_scan(conn: &mut Connection, scanned_files: &[String], items: &[DeliveryItem]) -> Result<()> {
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let tx = conn.transaction().context("开始台账事务失败")?;
+
+    for file in scanned_files {
+        let fingerprint = cached_fingerprint(&tx, file)
+            .or_else(|| file_fingerprint(Path::new(file)).ok())
+            .unwrap_or_default();
+        let customer_type = items
+            .iter()
+            .find(|i| &i.source_file == file)
+            .map(|i| i.customer_type.clone())
+            .unwrap_or_default();
+
+        tx.execute(
+            "INSERT INTO source_files (path, fingerprint, customer_type, first_seen, last_seen, archived)
+             VALUES (?1, ?2, ?3, ?4, ?4, 0)
+             ON CONFLICT (path) DO UPDATE SET
+                 fingerprint = excluded.fingerprint,
+                 customer_type = excluded.customer_type,
+                 last_seen = excluded.last_seen,
+                 archived = 0",
+            params![file, fingerprint, customer_type, now],
+        )?;
+        // 文件被移动或改名后，旧路径下的同一内容不再保留，避免重复
+        if !fingerprint.is_empty() {
+            let moved: Vec<String> = {
+                let mut stmt = tx.prepare("SELECT path FROM source_files WHERE fingerprint = ?1 AND path <> ?2")?;
+                let rows = stmt.query_map(params![fingerprint, file], |row| row.get(0))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+            for old in moved.iter().filter(|p| !Path::new(p).exists()) {
+                tx.execute("DELETE FROM delivery_lines WHERE source_file = ?1", params![old])?;
+                tx.execute("DELETE FROM delivery_notes WHERE source_file = ?1", params![old])?;
+                tx.execute("DELETE FROM line_corrections WHERE source_file = ?1", params![old])?;
+                tx.execute("DELETE FROM source_files WHERE path = ?1", params![old])?;
+            }
+        }
+
+        tx.execute("DELETE FROM delivery_lines WHERE source_file = ?1", params![file])?;
+        tx.execute("DELETE FROM delivery_notes WHERE source_file = ?1", params![file])?;
+        tx.execute("DELETE FROM line_corrections WHERE source_file = ?1", params![file])?;
+    }
+
+    for item in items {
+        let data = serde_json::to_string(item).context("序列化送货记录失败")?;
+        tx.execute(
+            "INSERT OR REPLACE INTO delivery_lines (
+                 source_file, source_row, sheet_name, customer, date, delivery_order_no, order_no,
+                 product_name, spec, quantity, unit, unit_price, amount, data, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                item.source_file,
+                item.source_row as i64,
+                item.sheet_name,
+                item.customer,
+                item.date,
+                item.delivery_order_no,
+                item.order_no,
+                item.product_name,
+                item.spec,
+                item.quantity,
+                item.unit,
+                item.unit_price,
+                item.amount,
+                data,
+                now,
+            ],
+        )?;
+        tx.execute(
+            "INSERT OR IGNORE INTO delivery_notes (source_file, delivery_order_no, customer, date, order_no)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![item.source_file, item.delivery_order_no, item.customer, item.date, item.order_no],
+        )?;
+        for change in &item.corrections {
+            tx.execute(
+                "INSERT OR REPLACE INTO line_corrections (source_file, source_row, field, original, corrected)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![item.source_file, item.source_row as i64, change.field, change.original, change.corrected],
+            )?;
+        }
+    }
+
+    // 源文件已被移走的数据保留，标记为已归档
+    let scanned: HashSet<&str> = scanned_files.iter().map(|f| f.as_str()).collect();
+    let known: Vec<String> = {
+        let mut stmt = tx.prepare("SELECT path FROM source_files WHERE archived = 0")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    for path in known {
+        if !scanned.contains(path.as_str()) && !Path::new(&path).exists() {
+            tx.execute("UPDATE source_files SET archived = 1 WHERE path = ?1", params![path])?;
+        }
+    }
+
+    tx.commit().context("保存台账失败")?;
+    Ok(())
+}
+
+/// 读取台账中原始数据目录 `root` 下的送货记录
+/// `include_archived` 为 true 时包括源文件已归档的历史数据
+pub fn load_items(conn: &Connection, root: &Path, include_archived: bool) -> Result<Vec<DeliveryItem>> {
+    let mut stmt = conn.prepare(
+        "SELECT l.source_file, l.data FROM delivery_lines l
+         LEFT JOIN source_files f ON f.path = l.source_file
+         WHERE ?1 OR COALESCE(f.archived, 0) = 0
+         ORDER BY l.source_file, l.source_row",
+    )?;
+    let rows = stmt.query_map(params![include_archived], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        let (source_file, data) = row?;
+        // 其他原始数据目录的记录不参与本目录的对账
+        if !Path::new(&source_file).starts_with(root) {
+            continue;
+        }
+        let item: DeliveryItem = serde_json::from_str(&data).context("台账数据格式错误")?;
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// 对账单源数据指纹 (SHA-256)，用于判断重新生成时数据是否变化
+/// 与条目顺序无关: 合并对账单的分组顺序和会话中的顺序都可能不同
+pub fn items_hash(items: &[DeliveryItem]) -> String {
+    let mut sorted: Vec<&DeliveryItem> = items.iter().collect();
+    sorted.sort_by(|a, b| {
+        (&a.source_file, a.source_row, &a.date).cmp(&(&b.source_file, b.source_row, &b.date))
+    });
+
+    let mut hasher = Sha256::new();
+    for item in sorted {
+        // 对账单分组是界面标注，不属于源数据
+        let mut item = item.clone();
+        item.statement_group.clear();
+        hasher.update(serde_json::to_vec(&item).unwrap_or_default());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// 查询客户某期对账单最近一次生成时的源数据指纹 (没有记录或旧版本未记录时为 None)
+/// 按客户 + 期间 + 是否合并对账单查找，另存的各个版本共用同一条记录
+pub fn statement_hash(conn: &Connection, customer: &str, period: &str, consolidated: bool) -> Option<String> {
+    conn.query_row(
+        "SELECT source_hash FROM statements
+         WHERE customer = ?1 AND period = ?2 AND consolidated = ?3 AND source_hash <> ''
+         ORDER BY generated_at DESC LIMIT 1",
+        params![customer, period, consolidated],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+}
+
+/// 记录已生成的对账单
+pub fn record_statement(
+    conn: &Connection,
+    customer: &str,
+    period: &str,
+    consolidated: bool,
+    file_path: &Path,
+    items: &[DeliveryItem],
+) -> Result<()> {
+    let total_amount: f64 = items.iter().map(|i| i.amount).sum();
+    conn.execute(
+        "INSERT OR REPLACE INTO statements
+             (file_path, customer, period, item_count, total_amount, generated_at, source_hash, consolidated)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            file_path.to_string_lossy(),
+            customer,
+            period,
+            items.len() as i64,
+            total_amount,
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            items_hash(items),
+            consolidated,
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processor::{raw_data_root, scan_excel_files};
+    use crate::exclusions::VoidList;
+
+    /// 测试用的内存数据库
+    fn memory_ledger() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn.execute_batch(scan_cache::SCHEMA).unwrap();
+        migrate(&conn).unwrap();
+        conn
+    }
+
+    /// 从当前目录到 `path` 的相对路径
+    fn relative_to_cwd(path: &Path) -> PathBuf {
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let mut relative = PathBuf::new();
+        for _ in cwd.components().skip(1) {
+            relative.push("..");
+        }
+        relative.join(path.strip_prefix("/").unwrap())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn relative_root_keeps_subdirectory_files() {
+        let dir = std::env::temp_dir().join(format!("ledger_relative_root_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("现金客户")).unwrap();
+        fs::write(dir.join("现金客户").join("a.xlsx"), b"a").unwrap();
+        fs::write(dir.join("b.xlsx"), b"b").unwrap();
+
+        let relative = relative_to_cwd(&dir.canonicalize().unwrap());
+        assert!(relative.is_relative());
+        let root = raw_data_root(&relative.to_string_lossy()).unwrap();
+        let (files, _) = scan_excel_files(&root, &VoidList::new(&[])).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|(p, _)| p.is_absolute() && p.starts_with(&root)));
+
+        let items: Vec<DeliveryItem> = files
+            .iter()
+            .map(|(p, customer_type)| DeliveryItem {
+                source_file: p.to_string_lossy().to_string(),
+                source_row: 9,
+                customer_type: customer_type.clone(),
+                ..Default::default()
+            })
+            .collect();
+        let scanned: Vec<String> = items.iter().map(|i| i.source_file.clone()).collect();
+        let mut conn = memory_ledger();
+        sync_scan(&mut conn, &scanned, &items).unwrap();
+
+        let loaded = load_items(&conn, &root, true).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert!(loaded.iter().any(|i| i.customer_type == "现金客户"));
+        assert!(loaded.iter().any(|i| i.customer_type == "默认"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn items_hash_ignores_input_order() {
+        let line = |file: &str, row: usize| DeliveryItem {
+            source_file: file.to_string(),
+            source_row: row,
+            amount: row as f64,
+            ..Default::default()
+        };
+        let items = vec![line("a.xlsx", 9), line("a.xlsx", 10), line("b.xlsx", 9)];
+        let mut reversed = items.clone();
+        reversed.reverse();
+        assert_eq!(items_hash(&items), items_hash(&reversed));
+
+        let mut changed = items.clone();
+        changed[1].amount += 1.0;
+        assert_ne!(items_hash(&items), items_hash(&changed));
+    }
+
+    /// 不上报进度的测试用进度
+    struct NoProgress;
+
+    impl crate::workers::Progress for NoProgress {
+        fn start(&self, _step: &str, _total: usize) {}
+        fn advance(&self, _file: &str, _status: &str) {}
+    }
+
+    #[test]
+    fn cached_parse_errors_keep_structured_error() {
+        let dir = std::env::temp_dir().join(format!("ledger_cached_error_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("broken.xlsx");
+        fs::write(&file, b"not a workbook").unwrap();
+        let files = vec![(file.clone(), "默认".to_string())];
+
+        let mut conn = memory_ledger();
+        let mut cache = scan_cache::ScanCache::load(&conn).unwrap();
+        let parsed = cache.parse_all(&files, 1, &NoProgress).remove(0).unwrap();
+        let Err(error) = parsed.items else {
+            panic!("broken workbook parsed");
+        };
+        cache.finish(&[]);
+        cache.save(&mut conn).unwrap();
+
+        let mut cache = scan_cache::ScanCache::load(&conn).unwrap();
+        let cached = cache.parse_all(&files, 1, &NoProgress).remove(0).unwrap();
+        assert!(cache.finish(&[]).added.is_empty());
+        let Err(cached_error) = cached.items else {
+            panic!("cached parse error lost");
+        };
+        assert_eq!(cached_error.code(), "WORKBOOK");
+        assert_eq!(cached_error.file(), Some(file.to_string_lossy().as_ref()));
+        assert_eq!(cached_error.to_string(), error.to_string());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,16 +1,53 @@
+mod corrections;
 mod data_processor;
+mod duplicates;
+mod error;
 mod excel_parser;
+mod exclusions;
+mod jobs;
+mod ledger;
 mod models;
+mod pricing;
+mod progress;
+mod scan_cache;
+mod sequence_check;
+mod sessions;
+mod spec_parser;
 mod statement_generator;
+mod validation;
+mod watcher;
+mod workers;
 
+use corrections::{
+    file_fingerprint, load_corrections, merge_correction, save_corrections,
+};
 use data_processor::{
-    group_by_customer_month, merge_delivery_data, scan_excel_files, validate_delivery_data,
+    generate_summary, group_by_customer_statement, group_by_parent_statement, parse_date,
+    raw_data_root, scan_excel_files, statement_group_key, validate_delivery_data, ValidationReport, UNASSIGNED_GROUP,
+};
+use duplicates::{find_duplicate_groups, is_duplicate_line};
+use error::{AppError, AppResult, ConfigAction, IoAction};
+use exclusions::VoidList;
+use models::{
+    AppConfig, Correction, DeliveryItem, DuplicateGroup, DuplicatePolicy, DuplicateResolution,
+    ExcludedDocument, FileAction, FileActionSetting, FileChanges, FileValidationError, ItemPage,
+    ItemQuery, OverwritePolicy, ProcessResult, ScanResult, Severity, StatementAction,
+    StatementOutcome, StatementSummary,
+};
+use pricing::PRICING_RULES;
+use statement_generator::{
+    generate_consolidated_statement, generate_statement, plan_output, statement_file_name, OutputPlan,
 };
-use models::{AppConfig, DeliveryItem, ProcessResult, ScanResult};
-use statement_generator::generate_statement;
+use validation::{split_by_severity, RuleDef, RULES};
 use std::fs;
-use std::path::PathBuf;
-use tauri::Emitter;
+use std::path::{Path, PathBuf};
+use scan_cache::ScanCache;
+use sessions::{ScanSession, SessionStore};
+use tauri::{Emitter, Manager};
+use watcher::WatcherState;
+use jobs::{JobGuard, JobRegistry};
+use progress::ProgressReporter;
+use workers::{run_parallel, worker_count, Progress};
 
 const CONFIG_FILE: &str = "config.json";
 
@@ -38,62 +75,182 @@ fn load_config() -> AppConfig {
 
 /// 保存配置
 #[tauri::command]
-fn save_config(config: AppConfig) -> Result<(), String> {
+fn save_config(config: AppConfig) -> AppResult<()> {
     let config_path = get_config_path();
 
     // 创建配置目录
     if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("创建配置目录失败: {}", e))?;
+        fs::create_dir_all(parent).map_err(|e| AppError::io(IoAction::CreateDir, parent, e))?;
     }
 
     // 序列化并保存
-    let content =
-        serde_json::to_string_pretty(&config).map_err(|e| format!("序列化配置失败: {}", e))?;
+    let content = serde_json::to_string_pretty(&config)
+        .map_err(|e| AppError::config(ConfigAction::SaveConfig, e))?;
 
-    fs::write(&config_path, content).map_err(|e| format!("保存配置失败: {}", e))?;
+    fs::write(&config_path, content).map_err(|e| AppError::io(IoAction::WriteFile, &config_path, e))?;
 
     Ok(())
 }
 
+/// 设置有问题文件的处理方式，返回更新后的配置
+#[tauri::command]
+fn set_file_action(file: String, action: FileAction) -> AppResult<AppConfig> {
+    let mut config = load_config();
+    config.file_actions.retain(|a| a.file != file);
+    if action != FileAction::default() {
+        config.file_actions.push(FileActionSetting { file, action });
+    }
+    save_config(config.clone())?;
+    Ok(config)
+}
+
+/// 指定一组跨文件重复行保留哪个文件中的行，返回更新后的配置
+#[tauri::command]
+fn resolve_duplicate(key: String, kept_file: String) -> AppResult<AppConfig> {
+    let mut config = load_config();
+    config.duplicate_resolutions.retain(|r| r.key != key);
+    config.duplicate_resolutions.push(DuplicateResolution { key, kept_file });
+    save_config(config.clone())?;
+    Ok(config)
+}
+
+/// 获取所有源数据修正
+#[tauri::command]
+fn list_corrections() -> Vec<Correction> {
+    load_corrections()
+}
+
+/// 保存源数据修正 (与同一文件同一行的已有修正合并)，记录当前文件指纹
+#[tauri::command]
+fn save_correction(mut correction: Correction) -> AppResult<Vec<Correction>> {
+    let source = std::path::Path::new(&correction.file);
+    correction.fingerprint =
+        file_fingerprint(source).map_err(|e| AppError::io(IoAction::ReadFile, source, format!("{:#}", e)))?;
+    correction.created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let mut corrections = load_corrections();
+    merge_correction(&mut corrections, correction);
+    save_corrections(&corrections)
+        .map_err(|e| AppError::config(ConfigAction::SaveCorrections, e))?;
+    Ok(corrections)
+}
+
+/// 删除源数据修正
+#[tauri::command]
+fn delete_correction(file: String, row: usize) -> AppResult<Vec<Correction>> {
+    let mut corrections = load_corrections();
+    corrections.retain(|c| !(c.file == file && c.row == row));
+    save_corrections(&corrections)
+        .map_err(|e| AppError::config(ConfigAction::SaveCorrections, e))?;
+    Ok(corrections)
+}
+
+/// 取消正在运行的任务 (处理完当前文件后停止)
+#[tauri::command]
+fn cancel_job(registry: tauri::State<'_, JobRegistry>, job_id: String) -> bool {
+    registry.cancel(&job_id)
+}
+
+/// 开始监视原始数据目录，自动导入新增或修改的送货单
+#[tauri::command]
+fn start_watching(
+    app: tauri::AppHandle,
+    watcher: tauri::State<'_, WatcherState>,
+    config: AppConfig,
+) -> AppResult<()> {
+    watcher.start(&app, config)
+}
+
+/// 停止监视原始数据目录
+#[tauri::command]
+fn stop_watching(watcher: tauri::State<'_, WatcherState>) {
+    watcher.stop();
+}
+
+/// 获取校验规则列表 (用于配置界面)
+#[tauri::command]
+fn list_validation_rules() -> &'static [RuleDef] {
+    RULES
+}
+
 /// 扫描并验证数据
 #[tauri::command]
-async fn scan_and_validate(config: AppConfig) -> Result<ScanResult, String> {
-    let raw_data_path = PathBuf::from(&config.raw_data_path);
+async fn scan_and_validate(
+    app: tauri::AppHandle,
+    registry: tauri::State<'_, JobRegistry>,
+    sessions: tauri::State<'_, SessionStore>,
+    config: AppConfig,
+    job_id: Option<String>,
+) -> AppResult<ScanResult> {
+    let job = JobGuard::start(&registry, job_id);
 
-    if !raw_data_path.exists() {
+    if !Path::new(&config.raw_data_path).exists() {
         return Ok(ScanResult {
             success: false,
             message: "原始数据目录不存在".to_string(),
-            total_files: 0,
-            valid_files: 0,
-            errors: vec![],
-            warnings: vec![],
-            items: vec![],
+            ..Default::default()
         });
     }
+    let raw_data_path = raw_data_root(&config.raw_data_path)?;
 
     // 扫描文件 (会自动识别一级目录作为客户类型)
-    let files_with_type =
-        scan_excel_files(&raw_data_path).map_err(|e| format!("扫描文件失败: {}", e))?;
+    // 进度条在函数返回 (包括出错提前返回) 时收到 done 事件
+    let progress = ProgressReporter::new(&app, job.cancelled.clone());
+    progress.start("scan", 0);
+    let void_list = VoidList::new(&config.void_rules);
+    let (files_with_type, mut excluded) = scan_excel_files(&raw_data_path, &void_list)?;
 
     if files_with_type.is_empty() {
         return Ok(ScanResult {
             success: true,
             message: "未找到 Excel 文件".to_string(),
-            total_files: 0,
-            valid_files: 0,
-            errors: vec![],
-            warnings: vec![],
-            items: vec![],
+            total_files: excluded.len(),
+            excluded,
+            ..Default::default()
         });
     }
 
-    // 验证数据
-    let (items, errors, warnings) = validate_delivery_data(&files_with_type);
+    // 验证数据 (只解析新增或变化的文件)
+    let (report, file_changes) =
+        validate_with_cache(&registry, &progress, &files_with_type, &excluded, &config, &void_list);
+    let items = report.items;
+    excluded.extend(report.excluded);
+
+    let (errors, warnings, infos) = split_by_severity(report.findings);
+
+    // 写入台账，界面展示台账中本目录的数据 (按设置包括源文件已归档的历史记录)
+    // 取消时只返回已处理的部分结果，不更新台账
+    let items = if report.cancelled {
+        items
+    } else {
+        update_ledger(
+            &registry,
+            &files_with_type,
+            &excluded,
+            items,
+            &raw_data_path,
+            config.include_archived,
+        )?
+    };
+
+    let products = generate_summary(&items);
+    let (session_id, statements) =
+        open_session(&sessions, items, &config, &warnings, &report.duplicates);
+
+    // 同一文件可能有多条错误/警告，按文件去重后计数
+    let problem_files = errors
+        .iter()
+        .chain(warnings.iter())
+        .map(|e| e.file.as_str())
+        .collect::<std::collections::HashSet<_>>()
+        .len();
+    progress.finish();
 
     Ok(ScanResult {
-        success: errors.is_empty(),
-        message: if errors.is_empty() {
+        success: errors.is_empty() && !report.cancelled,
+        message: if report.cancelled {
+            "扫描已取消，只显示已处理文件的数据".to_string()
+        } else if errors.is_empty() {
             if warnings.is_empty() {
                 "数据验证通过".to_string()
             } else {
@@ -101,168 +258,641 @@ async fn scan_and_validate(config: AppConfig) -> Result<ScanResult, String> {
             }
         } else {
             format!("发现 {} 个文件存在问题", errors.len())
+        } + &if report.rejected_rows.is_empty() {
+            String::new()
+        } else {
+            format!("，{} 行已拒绝", report.rejected_rows.len())
+        } + &if report.unassigned.is_empty() {
+            String::new()
+        } else {
+            format!("，{} 行日期待指定", report.unassigned.len())
         },
         total_files: files_with_type.len(),
-        valid_files: files_with_type.len() - errors.len() - warnings.len(),
+        valid_files: files_with_type.len().saturating_sub(problem_files),
         errors,
         warnings,
-        items,
+        infos,
+        rejected_rows: report.rejected_rows,
+        excluded,
+        sequence_issues: report.sequence_issues,
+        duplicates: report.duplicates,
+        price_anomalies: report.price_anomalies,
+        unassigned: report.unassigned,
+        file_changes,
+        cancelled: report.cancelled,
+        session_id,
+        statements,
+        products,
     })
 }
 
+/// 标注对账单分组后保存为扫描会话，返回会话 ID 和每个对账单分组的汇总
+/// 扫描结果保存在后端，界面只接收汇总，明细按需查询
+fn open_session(
+    sessions: &SessionStore,
+    mut items: Vec<DeliveryItem>,
+    config: &AppConfig,
+    warnings: &[FileValidationError],
+    duplicates: &[DuplicateGroup],
+) -> (String, Vec<StatementSummary>) {
+    for item in items.iter_mut() {
+        item.statement_group = format_year_month(&statement_group_key(item, config));
+    }
+    let session = ScanSession::new(items, config.customer_groups.clone(), warnings, duplicates);
+    let statements = session.summarize();
+    (sessions.insert(session), statements)
+}
+
+/// 查询扫描会话中的送货记录 (分页)
+#[tauri::command]
+fn query_session_items(
+    sessions: tauri::State<'_, SessionStore>,
+    session_id: String,
+    query: ItemQuery,
+) -> AppResult<ItemPage> {
+    let session = sessions.get(&session_id).ok_or(AppError::SessionExpired)?;
+    Ok(session.query(&query))
+}
+
+/// 按扫描会话中某个客户某个对账单分组的数据生成对账单
+/// 对账单已存在时按 `policy` 处理 (为空时使用配置中的覆盖策略)；跳过时返回 skipped 结果，由界面确认是否覆盖
 #[tauri::command]
 async fn generate_single_statement(
     app: tauri::AppHandle,
+    sessions: tauri::State<'_, SessionStore>,
     config: AppConfig,
-    items: Vec<DeliveryItem>,
+    session_id: String,
     customer: String,
     month: String,
-    overwrite: bool,
-) -> Result<ProcessResult, String> {
+    policy: Option<OverwritePolicy>,
+) -> AppResult<ProcessResult> {
+    // 与正在运行的扫描、生成任务和自动导入依次读写台账 (生成文件期间不持有台账锁)
+    let registry = app.state::<JobRegistry>();
+    let session = sessions.get(&session_id).ok_or(AppError::SessionExpired)?;
+    let items = session.statement_items(&customer, &month);
+    if items.is_empty() {
+        return Err(AppError::EmptyStatement {
+            customer,
+            period: month,
+        });
+    }
+
+    // 日期未确定的条目不能归入任何对账单
+    if items.iter().any(|i| parse_date(&i.date).is_err()) {
+        return Err(AppError::UnassignedDates {
+            customer,
+            period: month,
+        });
+    }
+
+    // 按设置阻止生成包含跨文件重复行的对账单
+    if config.duplicate_policy == DuplicatePolicy::BlockGeneration
+        && !find_duplicate_groups(&items).is_empty()
+    {
+        return Err(AppError::DuplicateLines {
+            customer,
+            period: month,
+        });
+    }
+
     let output_path = PathBuf::from(&config.output_path);
 
     // 创建输出目录
     if !output_path.exists() {
-        fs::create_dir_all(&output_path).map_err(|e| format!("创建输出目录失败: {}", e))?;
+        fs::create_dir_all(&output_path).map_err(|e| AppError::io(IoAction::CreateDir, &output_path, e))?;
     }
 
     // 创建客户文件夹
     let customer_dir = output_path.join(&customer);
     if !customer_dir.exists() {
-        fs::create_dir_all(&customer_dir).map_err(|e| format!("创建客户文件夹失败: {}", e))?;
+        fs::create_dir_all(&customer_dir).map_err(|e| AppError::io(IoAction::CreateDir, &customer_dir, e))?;
     }
 
     // 生成文件名 (使用传入的月份字符串，例如 2023年5月)
-    let statement_file = customer_dir.join(format!("statement_{}_{}.xlsx", customer, month));
-
-    // 检查文件是否存在
-    if statement_file.exists() && !overwrite {
-        return Err("FILE_EXISTS".to_string());
-    }
+    let consolidated = config.customer_groups.iter().any(|g| g.parent == customer);
+    let statement_file = customer_dir.join(statement_file_name(&customer, &month, consolidated));
+
+    // 文件已存在时按覆盖策略处理
+    let policy = policy.unwrap_or(config.overwrite_policy);
+    let ledger_conn = ledger::open_ledger();
+    let source_hash = ledger::items_hash(&items);
+    let recorded_hash = ledger_conn.as_ref().ok().and_then(|conn| {
+        let _ledger = registry.lock_ledger();
+        ledger::statement_hash(conn, &customer, &month, consolidated)
+    });
+
+    let (file, action, reason) = match plan_output(&statement_file, policy, recorded_hash.as_deref(), &source_hash) {
+        OutputPlan::Write { file, action, reason } => (file, action, reason),
+        OutputPlan::Skip { file, reason } => {
+            let _ = app.emit("log", format!("{}，跳过: {} {}", reason, customer, month));
+            return Ok(ProcessResult {
+                success: true,
+                message: format!("{}，未重新生成: {}", reason, file.to_string_lossy()),
+                generated_count: 0,
+                skipped_count: 1,
+                output_path: customer_dir.to_string_lossy().to_string(),
+                cancelled: false,
+                statements: vec![StatementOutcome {
+                    customer,
+                    period: month,
+                    file: file.to_string_lossy().to_string(),
+                    action: StatementAction::Skipped,
+                    reason,
+                }],
+            });
+        }
+    };
 
     let _ = app.emit("log", format!("正在生成对账单: {} {}", customer, month));
 
-    // 生成对账单
-    generate_statement(&items, &customer, &month, &statement_file, &config)
-        .map_err(|e| format!("生成对账单失败: {}", e))?;
+    // 生成对账单 (集团客户生成按子公司分段的合并对账单)
+    if consolidated {
+        generate_consolidated_statement(&items, &customer, &month, &file, &config)
+    } else {
+        generate_statement(&items, &customer, &month, &file, &config)
+    }?;
 
-    let _ = app.emit("log", format!("生成成功: {:?}", statement_file));
+    let _ = app.emit("log", format!("生成成功: {:?}", file));
+
+    let recorded = match &ledger_conn {
+        Ok(conn) => {
+            let _ledger = registry.lock_ledger();
+            ledger::record_statement(conn, &customer, &month, consolidated, &file, &items)
+        }
+        Err(e) => Err(anyhow::anyhow!("{}", e)),
+    };
+    if let Err(e) = recorded {
+        let _ = app.emit("log", format!("警告: 对账单记录写入台账失败: {}", e));
+    }
 
     Ok(ProcessResult {
         success: true,
-        message: format!("已生成: {}", statement_file.to_string_lossy()),
+        message: format!("已生成: {}", file.to_string_lossy()),
         generated_count: 1,
         skipped_count: 0,
         output_path: customer_dir.to_string_lossy().to_string(),
+        cancelled: false,
+        statements: vec![StatementOutcome {
+            customer,
+            period: month,
+            file: file.to_string_lossy().to_string(),
+            action,
+            reason,
+        }],
     })
 }
 
 #[tauri::command]
 async fn process_delivery_orders(
     app: tauri::AppHandle,
+    registry: tauri::State<'_, JobRegistry>,
     config: AppConfig,
-) -> Result<ProcessResult, String> {
+    job_id: Option<String>,
+) -> AppResult<ProcessResult> {
+    let job = JobGuard::start(&registry, job_id);
     // 先保存配置
     save_config(config.clone())?;
 
-    let raw_data_path = PathBuf::from(&config.raw_data_path);
+    let raw_data_path = raw_data_root(&config.raw_data_path)?;
     let output_path = PathBuf::from(&config.output_path);
 
     // 发送日志
     let _ = app.emit("log", "开始扫描 Excel 文件...");
 
     // 扫描 Excel 文件 (自动识别类型)
-    let files_with_type =
-        scan_excel_files(&raw_data_path).map_err(|e| format!("扫描文件失败: {}", e))?;
+    // 进度条在函数返回 (包括出错提前返回) 时收到 done 事件
+    let progress = ProgressReporter::new(&app, job.cancelled.clone());
+    progress.start("scan", 0);
+    let void_list = VoidList::new(&config.void_rules);
+    let (files_with_type, excluded) = scan_excel_files(&raw_data_path, &void_list)?;
 
     let _ = app.emit("log", format!("找到 {} 个 Excel 文件", files_with_type.len()));
+    if !excluded.is_empty() {
+        let _ = app.emit("log", format!("已排除 {} 个作废文件", excluded.len()));
+    }
 
     if files_with_type.is_empty() {
-        return Err("未找到任何 Excel 文件".to_string());
+        return Err(AppError::NoExcelFiles);
     }
 
-    // 合并数据
+    // 合并数据 (有错误的行和设置为排除的文件不参与生成)
     let _ = app.emit("log", "正在合并送货单数据...");
-    let all_items =
-        merge_delivery_data(&files_with_type).map_err(|e| format!("合并数据失败: {}", e))?;
+    let (report, file_changes) =
+        validate_with_cache(&registry, &progress, &files_with_type, &excluded, &config, &void_list);
+    let _ = app.emit(
+        "log",
+        format!(
+            "文件变化: 新增 {} 个，修改 {} 个，删除 {} 个",
+            file_changes.added.len(),
+            file_changes.changed.len(),
+            file_changes.removed.len()
+        ),
+    );
+    if report.cancelled {
+        let _ = app.emit("log", "任务已取消，未生成对账单");
+        return Ok(ProcessResult {
+            success: false,
+            message: "已取消".to_string(),
+            generated_count: 0,
+            skipped_count: 0,
+            output_path: output_path.to_string_lossy().to_string(),
+            cancelled: true,
+            statements: Vec::new(),
+        });
+    }
+    let all_items = report.items;
+    let duplicates = report.duplicates;
+    if !duplicates.is_empty() {
+        let _ = app.emit(
+            "log",
+            format!("发现 {} 组跨文件重复的送货记录", duplicates.len()),
+        );
+    }
+    if !report.excluded.is_empty() {
+        let _ = app.emit(
+            "log",
+            format!("已排除 {} 个作废送货单", report.excluded.len()),
+        );
+    }
+    if !report.rejected_rows.is_empty() {
+        let _ = app.emit(
+            "log",
+            format!("已拒绝 {} 行有错误的数据", report.rejected_rows.len()),
+        );
+    }
+    if !report.unassigned.is_empty() {
+        let _ = app.emit(
+            "log",
+            format!("{} 行日期无法识别，待指定日期后再生成", report.unassigned.len()),
+        );
+    }
+
+    // 计价检查已在校验中完成，设置为错误的行已被拒绝，这里只提示其余结果
+    for finding in report
+        .findings
+        .iter()
+        .filter(|f| PRICING_RULES.contains(&f.rule_id.as_str()) && f.severity != Severity::Error)
+    {
+        let _ = app.emit("log", format!("警告: {}", finding.error));
+    }
 
     let _ = app.emit("log", format!("共提取 {} 条数据记录", all_items.len()));
 
+    // 写入台账，按台账中本目录的数据生成 (按设置包括源文件已归档的历史记录)
+    let mut excluded = excluded;
+    excluded.extend(report.excluded.iter().cloned());
+    let all_items = update_ledger(
+        &registry,
+        &files_with_type,
+        &excluded,
+        all_items,
+        &raw_data_path,
+        config.include_archived,
+    )?;
+    let _ = app.emit("log", format!("台账共 {} 条数据记录", all_items.len()));
+
     if all_items.is_empty() {
-        return Err("未提取到任何数据".to_string());
+        return Err(AppError::NoData);
     }
 
     // 创建输出目录
-    fs::create_dir_all(&output_path).map_err(|e| format!("创建输出目录失败: {}", e))?;
+    fs::create_dir_all(&output_path).map_err(|e| AppError::io(IoAction::CreateDir, &output_path, e))?;
 
     // 按客户和月份分组
-    let grouped = group_by_customer_month(&all_items);
+    let grouped = group_by_customer_statement(&all_items, &config);
+    let consolidated = group_by_parent_statement(&grouped, &config);
     let _ = app.emit(
         "log",
         format!("共有 {} 个客户月份组合", grouped.len()),
     );
+    if !consolidated.is_empty() {
+        let _ = app.emit(
+            "log",
+            format!("共有 {} 个集团合并对账单", consolidated.len()),
+        );
+    }
 
     // 生成对账单
     let _ = app.emit("log", "开始生成对账单...");
     let mut generated_count = 0;
     let mut skipped_count = 0;
+    let mut failed_count = 0;
+
+    // 子公司单独对账单在前，集团合并对账单在后；按客户和分组排序，保证输出顺序稳定
+    let mut statements: Vec<_> = grouped
+        .iter()
+        .map(|(key, items)| (key, items, false))
+        .chain(consolidated.iter().map(|(key, items)| (key, items, true)))
+        .collect();
+    statements.sort_by(|(a, _, a_parent), (b, _, b_parent)| a_parent.cmp(b_parent).then(a.cmp(b)));
+
+    let ledger_conn = ledger::open_ledger();
+    let mut outcomes = Vec::new();
+    let mut skip = |customer: &str, period: String, file: &std::path::Path, reason: &str| {
+        let _ = app.emit("log", format!("{}，跳过: {} {}", reason, customer, period));
+        outcomes.push(StatementOutcome {
+            customer: customer.to_string(),
+            period,
+            file: file.to_string_lossy().to_string(),
+            action: StatementAction::Skipped,
+            reason: reason.to_string(),
+        });
+    };
 
-    for ((customer, year_month), items) in grouped.iter() {
+    let mut jobs = Vec::new();
+    for ((customer, year_month), items, is_consolidated) in statements {
         if customer.is_empty() {
             continue;
         }
 
+        // 日期未确定的分组不生成对账单
+        if year_month.contains(UNASSIGNED_GROUP) {
+            skip(customer, format_year_month(year_month), std::path::Path::new(""), "日期待指定");
+            skipped_count += 1;
+            continue;
+        }
+
+        // 设置为不单独出对账单的子公司
+        if !is_consolidated
+            && config
+                .customer_groups
+                .iter()
+                .any(|g| !g.child_statements && g.children.contains(customer))
+        {
+            continue;
+        }
+
+        // 按设置阻止生成包含跨文件重复行的对账单
+        if config.duplicate_policy == DuplicatePolicy::BlockGeneration
+            && items.iter().any(|i| is_duplicate_line(i, &duplicates))
+        {
+            skip(customer, format_year_month(year_month), std::path::Path::new(""), "存在重复记录");
+            skipped_count += 1;
+            continue;
+        }
+
         // 创建客户文件夹
         let customer_dir = output_path.join(customer);
-        fs::create_dir_all(&customer_dir).map_err(|e| format!("创建客户文件夹失败: {}", e))?;
+        fs::create_dir_all(&customer_dir).map_err(|e| AppError::io(IoAction::CreateDir, &customer_dir, e))?;
 
         // 生成文件名
         let statement_file =
-            customer_dir.join(format!("statement_{}_{}.xlsx", customer, year_month));
-
-        // 检查文件是否已存在
-        if statement_file.exists() {
-            let _ = app.emit(
-                "log",
-                format!("已存在，跳过: {} {}", customer, year_month),
-            );
-            skipped_count += 1;
-            continue;
-        }
+            customer_dir.join(statement_file_name(customer, year_month, is_consolidated));
 
-        // 格式化年月
-        let year_month_str = format_year_month(year_month);
+        // 文件已存在时按覆盖策略处理
+        let recorded_hash = ledger_conn.as_ref().ok().and_then(|conn| {
+            let _ledger = registry.lock_ledger();
+            ledger::statement_hash(conn, customer, &format_year_month(year_month), is_consolidated)
+        });
+        let plan = plan_output(
+            &statement_file,
+            config.overwrite_policy,
+            recorded_hash.as_deref(),
+            &ledger::items_hash(items),
+        );
+        let (file, action, reason) = match plan {
+            OutputPlan::Write { file, action, reason } => (file, action, reason),
+            OutputPlan::Skip { file, reason } => {
+                skip(customer, format_year_month(year_month), &file, &reason);
+                skipped_count += 1;
+                continue;
+            }
+        };
+
+        jobs.push(StatementJob {
+            customer,
+            // 格式化年月
+            period: format_year_month(year_month),
+            file,
+            items,
+            consolidated: is_consolidated,
+            action,
+            reason,
+        });
+    }
 
-        let _ = app.emit("log", format!("生成: {} {}", customer, year_month_str));
+    // 并行生成对账单
+    progress.start("generate", jobs.len());
+    let results = run_parallel(&jobs, worker_count(config.max_workers), |job| {
+        // 取消后不再开始新的对账单
+        if progress.is_cancelled() {
+            return None;
+        }
+        let result = if job.consolidated {
+            generate_consolidated_statement(job.items, job.customer, &job.period, &job.file, &config)
+        } else {
+            generate_statement(job.items, job.customer, &job.period, &job.file, &config)
+        };
+        let status = if result.is_ok() { "ok" } else { "error" };
+        progress.advance(&job.file.to_string_lossy(), status);
+        Some(result)
+    });
+
+    let mut cancelled = false;
+    for (job, result) in jobs.iter().zip(results) {
+        let Some(result) = result else {
+            cancelled = true;
+            continue;
+        };
+        // 单个对账单失败不影响其他对账单，记录原因后继续
+        if let Err(e) = result {
+            let _ = app.emit("log", format!("生成失败: {} {}: {}", job.customer, job.period, e));
+            outcomes.push(StatementOutcome {
+                customer: job.customer.to_string(),
+                period: job.period.clone(),
+                file: job.file.to_string_lossy().to_string(),
+                action: StatementAction::Failed,
+                reason: e.to_string(),
+            });
+            failed_count += 1;
+            continue;
+        }
 
-        // 生成对账单
-        generate_statement(items, customer, &year_month_str, &statement_file, &config)
-            .map_err(|e| format!("生成对账单失败: {}", e))?;
+        let label = match job.action {
+            StatementAction::Replaced => "覆盖",
+            StatementAction::Versioned => "新版本",
+            _ => "生成",
+        };
+        let _ = app.emit("log", format!("{}: {} {}", label, job.customer, job.period));
+
+        let recorded = match &ledger_conn {
+            Ok(conn) => {
+                let _ledger = registry.lock_ledger();
+                ledger::record_statement(
+                    conn,
+                    job.customer,
+                    &job.period,
+                    job.consolidated,
+                    &job.file,
+                    job.items,
+                )
+            }
+            Err(e) => Err(anyhow::anyhow!("{}", e)),
+        };
+        if let Err(e) = recorded {
+            let _ = app.emit("log", format!("警告: 对账单记录写入台账失败: {}", e));
+        }
 
+        outcomes.push(StatementOutcome {
+            customer: job.customer.to_string(),
+            period: job.period.clone(),
+            file: job.file.to_string_lossy().to_string(),
+            action: job.action,
+            reason: job.reason.clone(),
+        });
         generated_count += 1;
     }
 
-    let _ = app.emit("log", "所有对账单生成完成！");
+    if cancelled {
+        let _ = app.emit("log", "任务已取消，未开始的对账单不再生成");
+    } else {
+        let _ = app.emit("log", "所有对账单生成完成！");
+    }
     let _ = app.emit("log", format!("新生成: {} 个对账单", generated_count));
     let _ = app.emit("log", format!("已跳过: {} 个对账单", skipped_count));
+    if failed_count > 0 {
+        let _ = app.emit("log", format!("生成失败: {} 个对账单", failed_count));
+    }
+    progress.finish();
 
     Ok(ProcessResult {
-        success: true,
-        message: "处理完成".to_string(),
+        success: !cancelled && failed_count == 0,
+        message: if cancelled {
+            "已取消".to_string()
+        } else if failed_count > 0 {
+            format!("处理完成，{} 个对账单生成失败", failed_count)
+        } else {
+            "处理完成".to_string()
+        },
         generated_count,
         skipped_count,
         output_path: output_path.to_string_lossy().to_string(),
+        cancelled,
+        statements: outcomes,
     })
 }
 
+/// 待生成的对账单
+struct StatementJob<'a> {
+    customer: &'a str,
+    period: String,
+    file: PathBuf,
+    items: &'a [DeliveryItem],
+    consolidated: bool,
+    action: StatementAction,
+    /// 覆盖或另存新版本的原因
+    reason: String,
+}
+
+/// 使用解析缓存验证数据，返回校验结果和与上次扫描相比的文件变化
+/// 台账不可用时退回到全部重新解析；只在保存缓存时持有台账锁
+fn validate_with_cache(
+    registry: &JobRegistry,
+    progress: &ProgressReporter,
+    files: &[(PathBuf, String)],
+    excluded: &[ExcludedDocument],
+    config: &AppConfig,
+    void_list: &VoidList,
+) -> (ValidationReport, FileChanges) {
+    let mut conn = ledger::open_ledger().ok();
+    let mut cache = conn
+        .as_ref()
+        .and_then(|c| ScanCache::load(c).ok())
+        .unwrap_or_else(ScanCache::empty);
+
+    let report = validate_delivery_data(
+        files,
+        config,
+        void_list,
+        &load_corrections(),
+        &mut cache,
+        worker_count(config.max_workers),
+        progress,
+    );
+    let changes = cache.finish(&scanned_paths(files, excluded));
+    if let Some(conn) = conn.as_mut() {
+        let _ledger = registry.lock_ledger();
+        let _ = cache.save(conn);
+    }
+
+    (report, changes)
+}
+
+/// 本次扫描到的全部文件 (包括已作废或排除的文件)
+fn scanned_paths(files: &[(PathBuf, String)], excluded: &[ExcludedDocument]) -> Vec<String> {
+    let mut scanned: Vec<String> = files
+        .iter()
+        .map(|(path, _)| path.to_string_lossy().to_string())
+        .chain(excluded.iter().map(|d| d.file.clone()))
+        .collect();
+    scanned.sort();
+    scanned.dedup();
+    scanned
+}
+
+/// 将本次扫描的有效数据写入台账，返回台账中当前原始数据目录下的数据
+/// (按设置包括源文件已归档的历史记录)
+fn update_ledger(
+    registry: &JobRegistry,
+    files: &[(PathBuf, String)],
+    excluded: &[ExcludedDocument],
+    items: Vec<DeliveryItem>,
+    root: &Path,
+    include_archived: bool,
+) -> AppResult<Vec<DeliveryItem>> {
+    let scanned = scanned_paths(files, excluded);
+
+    let mut conn = ledger::open_ledger().map_err(AppError::ledger)?;
+    let _ledger = registry.lock_ledger();
+    ledger::sync_scan(&mut conn, &scanned, &items).map_err(AppError::ledger)?;
+    ledger::load_items(&conn, root, include_archived).map_err(AppError::ledger)
+}
+
 fn format_year_month(year_month: &str) -> String {
+    if year_month == UNASSIGNED_GROUP {
+        return "日期待指定".to_string();
+    }
+
+    // 按分店: "2024-01@东城店" 格式化为 "2024年1月 东城店"
+    if let Some((period, branch)) = year_month.split_once('@') {
+        let branch = if branch.is_empty() { "未注明分店" } else { branch };
+        return format!("{} {}", format_year_month(period), branch);
+    }
+
+    // 按订单号: "PO_123" 格式化为 "订单号123"
+    if let Some(order_no) = year_month.strip_prefix("PO_") {
+        return if order_no.is_empty() {
+            "无订单号".to_string()
+        } else {
+            format!("订单号{}", order_no)
+        };
+    }
+
     // 将 "2024-01" 格式化为 "2024年1月"
-    let parts: Vec<&str> = year_month.split('-').collect();
+    // 将 "2024-01_2023-12-26_2024-01-25" 格式化为 "2024年1月(2023-12-26至2024-01-25)"
+    let mut sections = year_month.splitn(3, '_');
+    let month_part = sections.next().unwrap_or_default();
+    let range = match (sections.next(), sections.next()) {
+        (Some(start), Some(end)) => format!("({}至{})", start, end),
+        _ => String::new(),
+    };
+
+    let parts: Vec<&str> = month_part.split('-').collect();
     if parts.len() == 2 {
+        // 按季度: "2024-Q1" 格式化为 "2024年第1季度"
+        if let Some(quarter) = parts[1].strip_prefix('Q') {
+            return format!("{}年第{}季度", parts[0], quarter);
+        }
         if let (Ok(year), Ok(month)) = (parts[0].parse::<i32>(), parts[1].parse::<u32>()) {
-            return format!("{}年{}月", year, month);
+            return format!("{}年{}月{}", year, month, range);
         }
     }
+
+    // 按年: "2024" 格式化为 "2024年"
+    if month_part.len() == 4 && month_part.parse::<i32>().is_ok() {
+        return format!("{}年", month_part);
+    }
+
     year_month.to_string()
 }
 
@@ -271,13 +901,62 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(JobRegistry::default())
+        .manage(WatcherState::default())
+        .manage(SessionStore::default())
+        .setup(|app| {
+            let handle = app.handle().clone();
+
+            // 启动时创建台账数据库和表结构
+            if let Err(e) = ledger::init_ledger() {
+                let _ = handle.emit("log", format!("初始化台账数据库失败: {:#}", e));
+            }
+
+            // 按设置启动时自动监视原始数据目录
+            let config = load_config();
+            if config.auto_ingest {
+                if let Err(e) = app.state::<WatcherState>().start(&handle, config) {
+                    let _ = handle.emit("log", format!("启动目录监视失败: {}", e));
+                }
+            }
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             load_config,
             save_config,
+            list_validation_rules,
+            set_file_action,
+            resolve_duplicate,
+            list_corrections,
+            save_correction,
+            delete_correction,
             process_delivery_orders,
             scan_and_validate,
-            generate_single_statement
+            query_session_items,
+            generate_single_statement,
+            cancel_job,
+            start_watching,
+            stop_watching
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_statement_group_labels() {
+        assert_eq!(format_year_month("2024-01"), "2024年1月");
+        assert_eq!(
+            format_year_month("2024-01_2023-12-26_2024-01-25"),
+            "2024年1月(2023-12-26至2024-01-25)"
+        );
+        assert_eq!(format_year_month("2024-Q1"), "2024年第1季度");
+        assert_eq!(format_year_month("2024"), "2024年");
+        assert_eq!(format_year_month("2024-01@东城店"), "2024年1月 东城店");
+        assert_eq!(format_year_month("PO_123"), "订单号123");
+        assert_eq!(format_year_month(UNASSIGNED_GROUP), "日期待指定");
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// 送货单条目
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DeliveryItem {
     /// 货名
     pub product_name: String,
@@ -25,22 +25,138 @@ pub struct DeliveryItem {
     pub order_no: String,
     /// 源文件
     pub source_file: String,
+    /// 源文件中的行号 (从 1 开始)
+    #[serde(default)]
+    pub source_row: usize,
+    /// 源工作表名称
+    #[serde(default)]
+    pub sheet_name: String,
     /// 客户类型 (monthly: 月结, cash: 现金)
     #[serde(default = "default_customer_type")]
     pub customer_type: String,
+    /// 解析后的规格 (宽度/长度/厚度/包装重量)
+    #[serde(default)]
+    pub spec_info: Option<SpecInfo>,
+    /// 计价数量 (按重量/面积计价时由送货数量换算)
+    #[serde(default)]
+    pub billing_quantity: f64,
+    /// 计价单位
+    #[serde(default)]
+    pub billing_unit: String,
+    /// 单价是否由价目表补录
+    #[serde(default)]
+    pub price_filled: bool,
+    /// 送货单上没有单价 (单价列缺失或为空)
+    #[serde(default)]
+    pub price_missing: bool,
+    /// 数量单元格无法识别为数字时的原始内容 (例如误填的规格 "160*1000米")，为空表示数量正常
+    #[serde(default)]
+    pub unreadable_quantity: String,
+    /// 是否为赠品
+    #[serde(default)]
+    pub is_free: bool,
+    /// 备注
+    #[serde(default)]
+    pub remark: String,
+    /// 已应用的修正
+    #[serde(default)]
+    pub corrections: Vec<FieldChange>,
+    /// 收货分店
+    #[serde(default)]
+    pub branch: String,
+    /// 所属对账单分组 (例如 "2024年1月"、"2024年1月(2023-12-26至2024-01-25)"、"2024年第1季度")
+    #[serde(default)]
+    pub statement_group: String,
 }
 
 fn default_customer_type() -> String {
     "monthly".to_string()
 }
 
+/// 规格数值及单位 (单位已标准化: mm/cm/m/um/丝/kg/g/斤)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecValue {
+    pub value: f64,
+    pub unit: String,
+}
+
+/// 结构化规格
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpecInfo {
+    /// 宽度
+    pub width: Option<SpecValue>,
+    /// 长度
+    pub length: Option<SpecValue>,
+    /// 厚度
+    pub thickness: Option<SpecValue>,
+    /// 每包/卷重量
+    pub pack_weight: Option<SpecValue>,
+    /// 包装单位 (例如 "25kg/包" 中的 "包")
+    pub pack_unit: String,
+}
+
+/// 计价方式
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingBasis {
+    /// 按送货数量
+    #[default]
+    Quantity,
+    /// 按重量 (kg)
+    Weight,
+    /// 按面积 (㎡)
+    Area,
+}
+
+/// 产品计价规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricingRule {
+    /// 货名关键字
+    pub product_keyword: String,
+    /// 规格关键字 (为空时匹配所有规格)
+    #[serde(default)]
+    pub spec_keyword: String,
+    pub basis: PricingBasis,
+    /// 每个送货单位折合的计价数量 (为 0 时根据规格自动换算)
+    #[serde(default)]
+    pub factor: f64,
+    /// 计价单位 (为空时使用 kg / ㎡)
+    #[serde(default)]
+    pub billing_unit: String,
+}
+
+/// 客户价目表条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceListEntry {
+    /// 客户
+    pub customer: String,
+    /// 货名
+    pub product_name: String,
+    /// 规格 (为空时适用于所有规格)
+    #[serde(default)]
+    pub spec: String,
+    /// 合同单价
+    pub unit_price: f64,
+    /// 生效日期 (YYYY-MM-DD)
+    pub effective_from: String,
+    /// 失效日期 (为空表示长期有效)
+    #[serde(default)]
+    pub effective_to: String,
+}
+
 /// 汇总数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SummaryItem {
     pub product_name: String,
     pub spec: String,
+    /// 厚度分组 (例如 "0.05mm")，无法识别时为空
+    #[serde(default)]
+    pub gauge: String,
     pub unit: String,
     pub quantity: f64,
+    /// 其中赠品数量 (不参与平均单价计算)
+    #[serde(default)]
+    pub free_quantity: f64,
     pub average_price: f64,
     pub amount: f64,
     pub customers: String,
@@ -61,6 +177,62 @@ pub struct AppConfig {
     pub raw_data_path: String,
     /// 输出路径
     pub output_path: String,
+    /// 产品计价规则
+    #[serde(default)]
+    pub pricing_rules: Vec<PricingRule>,
+    /// 客户价目表
+    #[serde(default)]
+    pub price_list: Vec<PriceListEntry>,
+    /// 历史单价异常倍数 (单价高于历史中位数该倍数或低于其倒数时提示)
+    #[serde(default = "default_price_anomaly_ratio")]
+    pub price_anomaly_ratio: f64,
+    /// 校验规则配置
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    /// 有问题文件的处理方式
+    #[serde(default)]
+    pub file_actions: Vec<FileActionSetting>,
+    /// 作废清单
+    #[serde(default = "default_void_rules")]
+    pub void_rules: Vec<VoidRule>,
+    /// 送货单号单本格式
+    #[serde(default)]
+    pub order_no_books: Vec<OrderNoBook>,
+    /// 跨文件重复行的处理策略
+    #[serde(default)]
+    pub duplicate_policy: DuplicatePolicy,
+    /// 用户逐组指定的重复行处理 (优先于重复处理策略)
+    #[serde(default)]
+    pub duplicate_resolutions: Vec<DuplicateResolution>,
+    /// 客户账期设置
+    #[serde(default)]
+    pub billing_cycles: Vec<BillingCycle>,
+    /// 客户对账单分组设置
+    #[serde(default)]
+    pub groupings: Vec<CustomerGrouping>,
+    /// 集团客户合并对账设置
+    #[serde(default)]
+    pub customer_groups: Vec<CustomerGroup>,
+    /// 并行解析/生成的工作线程数 (0 为按 CPU 核数)
+    #[serde(default)]
+    pub max_workers: usize,
+    /// 监视原始数据目录，自动导入新增或修改的送货单
+    #[serde(default)]
+    pub auto_ingest: bool,
+    /// 对账时包括源文件已归档的台账历史数据
+    #[serde(default = "default_include_archived")]
+    pub include_archived: bool,
+    /// 对账单已存在时的处理方式
+    #[serde(default)]
+    pub overwrite_policy: OverwritePolicy,
+}
+
+fn default_price_anomaly_ratio() -> f64 {
+    3.0
+}
+
+fn default_include_archived() -> bool {
+    true
 }
 
 impl Default for AppConfig {
@@ -72,6 +244,22 @@ impl Default for AppConfig {
             fax: "83637787".to_string(),
             raw_data_path: "raw-data".to_string(),
             output_path: "output".to_string(),
+            pricing_rules: Vec::new(),
+            price_list: Vec::new(),
+            price_anomaly_ratio: default_price_anomaly_ratio(),
+            validation: ValidationConfig::default(),
+            file_actions: Vec::new(),
+            void_rules: default_void_rules(),
+            order_no_books: Vec::new(),
+            duplicate_policy: DuplicatePolicy::default(),
+            duplicate_resolutions: Vec::new(),
+            billing_cycles: Vec::new(),
+            groupings: Vec::new(),
+            customer_groups: Vec::new(),
+            max_workers: 0,
+            auto_ingest: false,
+            include_archived: default_include_archived(),
+            overwrite_policy: OverwritePolicy::default(),
         }
     }
 }
@@ -79,10 +267,24 @@ impl Default for AppConfig {
 /// 进度信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProgressInfo {
+    /// 阶段: scan / parse / validate / generate；done 表示任务结束
     pub step: String,
     pub current: usize,
+    /// 该阶段总数 (未知时为 0)
     pub total: usize,
     pub message: String,
+    /// 刚完成的文件或对账单
+    #[serde(default)]
+    pub file: String,
+    /// 该文件的处理结果 (例如 parsed / cached / error / ok)
+    #[serde(default)]
+    pub status: String,
+    /// 本阶段已用时间 (秒)
+    #[serde(default)]
+    pub elapsed_seconds: f64,
+    /// 本阶段预计剩余时间 (秒)
+    #[serde(default)]
+    pub eta_seconds: Option<f64>,
 }
 
 /// 处理结果
@@ -93,6 +295,61 @@ pub struct ProcessResult {
     pub generated_count: usize,
     pub skipped_count: usize,
     pub output_path: String,
+    /// 任务被取消 (已生成的对账单保留，未开始的不再生成)
+    #[serde(default)]
+    pub cancelled: bool,
+    /// 每个对账单的处理结果
+    #[serde(default)]
+    pub statements: Vec<StatementOutcome>,
+}
+
+/// 对账单已存在时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// 跳过已存在的对账单
+    #[default]
+    Skip,
+    /// 覆盖
+    Overwrite,
+    /// 源数据变化时才覆盖
+    OverwriteIfChanged,
+    /// 另存为新版本 (例如 statement_客户_2024年1月_v2.xlsx)，源数据未变化时跳过
+    Versioned,
+}
+
+/// 对账单的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementAction {
+    Created,
+    Replaced,
+    Versioned,
+    Skipped,
+    Failed,
+}
+
+/// 单个对账单的处理结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementOutcome {
+    pub customer: String,
+    pub period: String,
+    /// 写入 (或跳过) 的文件，未确定文件名时为空
+    pub file: String,
+    pub action: StatementAction,
+    /// 跳过、覆盖或失败的原因
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// 校验结果严重级别
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    #[default]
+    Warning,
+    Info,
 }
 
 /// 文件验证错误
@@ -100,10 +357,366 @@ pub struct ProcessResult {
 pub struct FileValidationError {
     pub file: String,
     pub error: String,
+    /// 产生该结果的校验规则 id
+    #[serde(default)]
+    pub rule_id: String,
+    #[serde(default)]
+    pub severity: Severity,
+    /// 问题所在行 (文件级问题为空)
+    #[serde(default)]
+    pub row: Option<usize>,
 }
 
-/// 扫描结果
+/// 有问题文件的处理方式
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    /// 接受有效行，拒绝有错误的行
+    #[default]
+    AcceptValidRows,
+    /// 排除整个文件
+    ExcludeFile,
+}
+
+/// 单个文件的处理方式设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileActionSetting {
+    pub file: String,
+    pub action: FileAction,
+}
+
+/// 源数据修正 (按文件指纹 + 行号匹配，未填写的字段保持原值)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Correction {
+    /// 修正时的文件路径
+    pub file: String,
+    pub row: usize,
+    /// 修正时的文件内容指纹
+    #[serde(default)]
+    pub fingerprint: String,
+    #[serde(default)]
+    pub customer: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub product_name: Option<String>,
+    #[serde(default)]
+    pub quantity: Option<f64>,
+    #[serde(default)]
+    pub unit_price: Option<f64>,
+    /// 修正说明
+    #[serde(default)]
+    pub note: String,
+    /// 修正时间
+    #[serde(default)]
+    pub created_at: String,
+}
+
+/// 字段修改记录 (用于对账单审计)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub original: String,
+    pub corrected: String,
+}
+
+/// 作废清单规则类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoidRuleKind {
+    /// 指定文件 (完整路径或文件名)
+    File,
+    /// 指定送货单号
+    DeliveryOrderNo,
+    /// 文件名或工作表名匹配 (正则)
+    Pattern,
+}
+
+/// 作废清单规则 (已作废的送货单不参与对账)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoidRule {
+    pub kind: VoidRuleKind,
+    pub value: String,
+    /// 限定客户 (仅对送货单号生效，为空表示所有客户)
+    #[serde(default)]
+    pub customer: String,
+    /// 作废原因
+    #[serde(default)]
+    pub reason: String,
+}
+
+fn default_void_rules() -> Vec<VoidRule> {
+    vec![VoidRule {
+        kind: VoidRuleKind::Pattern,
+        value: "作废".to_string(),
+        customer: String::new(),
+        reason: String::new(),
+    }]
+}
+
+/// 被排除的送货单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedDocument {
+    pub file: String,
+    /// 送货单号 (整个文件被排除时可能为空)
+    pub delivery_order_no: String,
+    pub reason: String,
+}
+
+/// 送货单号单本格式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderNoBook {
+    /// 客户 (为空表示所有客户)
+    #[serde(default)]
+    pub customer: String,
+    /// 单号格式 (正则)，可用命名组 book 区分单本、seq 表示流水号
+    pub pattern: String,
+}
+
+/// 送货单号顺序问题类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceIssueKind {
+    /// 缺号
+    Gap,
+    /// 单号与日期顺序不一致
+    OutOfOrder,
+    /// 单号格式不符
+    Malformed,
+}
+
+/// 送货单号顺序问题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceIssue {
+    pub customer: String,
+    /// 单本 (单号中流水号之前的部分)
+    pub book: String,
+    pub kind: SequenceIssueKind,
+    pub delivery_order_no: String,
+    pub file: String,
+    pub message: String,
+}
+
+/// 跨文件重复行的处理策略
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// 仅提示，全部保留
+    #[default]
+    Warn,
+    /// 保留最早的文件
+    KeepFirst,
+    /// 保留最新修改的文件
+    KeepNewest,
+    /// 存在重复时不生成相关对账单
+    BlockGeneration,
+}
+
+/// 重复行所在位置
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateEntry {
+    pub file: String,
+    pub row: usize,
+    pub delivery_order_no: String,
+    /// 文件修改时间
+    pub modified: String,
+    /// 是否保留
+    pub kept: bool,
+}
+
+/// 内容相同的一组重复行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub customer: String,
+    pub date: String,
+    pub product_name: String,
+    pub spec: String,
+    pub quantity: f64,
+    pub amount: f64,
+    pub entries: Vec<DuplicateEntry>,
+    /// 分组标识 (内容键)，用于保存用户的处理选择
+    #[serde(default)]
+    pub key: String,
+    /// 用户已指定保留哪个文件
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+/// 用户对一组重复行的处理: 只保留指定文件中的行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateResolution {
+    /// 重复分组标识
+    pub key: String,
+    pub kept_file: String,
+}
+
+/// 自定义账期
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BillingPeriod {
+    /// 开始日期 (YYYY-MM-DD，含)
+    pub start: String,
+    /// 结束日期 (YYYY-MM-DD，含)
+    pub end: String,
+}
+
+/// 客户账期设置 (未配置的客户按自然月对账)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BillingCycle {
+    pub customer: String,
+    /// 每月结账日 (1-31，例如 25 表示上月 26 日至本月 25 日；当月没有这一天时以月末结账；0 按自然月)
+    #[serde(default)]
+    pub cutoff_day: u32,
+    /// 自定义账期 (优先于结账日)
+    #[serde(default)]
+    pub periods: Vec<BillingPeriod>,
+}
+
+/// 对账单分组方式
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupingStrategy {
+    /// 按月 (或客户账期)
+    #[default]
+    Month,
+    /// 按季度
+    Quarter,
+    /// 按年
+    Year,
+    /// 每个订单号一张
+    OrderNo,
+    /// 按收货分店，每个分店每月一张
+    Branch,
+}
+
+/// 客户对账单分组设置 (未配置的客户按月)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerGrouping {
+    pub customer: String,
+    pub strategy: GroupingStrategy,
+}
+
+/// 集团客户: 多个子公司 (各自作为独立客户) 合并对账
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerGroup {
+    /// 集团名称 (合并对账单的抬头)
+    pub parent: String,
+    /// 子公司客户名称
+    pub children: Vec<String>,
+    /// 是否同时为每个子公司单独生成对账单
+    #[serde(default = "default_true")]
+    pub child_statements: bool,
+}
+
+/// 与上次扫描相比的文件变化
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileChanges {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// 自动导入结果 (监视到原始数据目录变化后发送给界面)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IngestResult {
+    /// 新增或修改的文件
+    pub changed: Vec<String>,
+    /// 已删除的文件 (台账中的历史数据保留)
+    pub removed: Vec<String>,
+    /// 本次导入的有效行数
+    pub imported_rows: usize,
+    pub errors: Vec<FileValidationError>,
+    pub warnings: Vec<FileValidationError>,
+    /// 变化的文件中被拒绝的行
+    #[serde(default)]
+    pub rejected_rows: Vec<RejectedRow>,
+    /// 变化的文件中日期待指定的条目
+    #[serde(default)]
+    pub unassigned: Vec<DeliveryItem>,
+    /// 台账中本目录全部数据的跨文件重复行
+    #[serde(default)]
+    pub duplicates: Vec<DuplicateGroup>,
+    /// 台账中本目录全部数据的送货单号问题
+    #[serde(default)]
+    pub sequence_issues: Vec<SequenceIssue>,
+    /// 导入后的新扫描会话，界面据此刷新而不必重新扫描
+    #[serde(default)]
+    pub session_id: String,
+    #[serde(default)]
+    pub statements: Vec<StatementSummary>,
+}
+
+/// 被拒绝的行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedRow {
+    pub file: String,
+    pub row: usize,
+    pub item: DeliveryItem,
+    /// 拒绝原因
+    pub reasons: Vec<String>,
+}
+
+/// 单条校验规则配置 (未配置的规则使用默认设置)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleConfig {
+    pub id: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 覆盖默认严重级别
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    /// 规则参数
+    #[serde(default)]
+    pub params: serde_json::Value,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 屏蔽某条规则的部分结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suppression {
+    pub rule_id: String,
+    /// 文件路径包含该文字时屏蔽 (为空表示所有文件)
+    #[serde(default)]
+    pub file: String,
+    /// 结果描述包含该文字时屏蔽 (为空表示所有结果)
+    #[serde(default)]
+    pub message: String,
+}
+
+/// 校验配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+    #[serde(default)]
+    pub suppressions: Vec<Suppression>,
+}
+
+/// 历史单价异常 (与该客户同一产品/规格近期单价相比偏差过大)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceAnomaly {
+    pub file: String,
+    pub customer: String,
+    pub product_name: String,
+    pub spec: String,
+    pub date: String,
+    pub delivery_order_no: String,
+    /// 本次单价
+    pub unit_price: f64,
+    /// 历史单价中位数
+    pub history_median: f64,
+    /// 历史最低单价
+    pub history_min: f64,
+    /// 历史最高单价
+    pub history_max: f64,
+    /// 参与比较的历史记录数
+    pub history_count: usize,
+}
+
+/// 扫描结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ScanResult {
     pub success: bool,
     pub message: String,
@@ -111,5 +724,140 @@ pub struct ScanResult {
     pub valid_files: usize,
     pub errors: Vec<FileValidationError>,
     pub warnings: Vec<FileValidationError>,
-    pub items: Vec<DeliveryItem>,
+    /// 提示 (不影响生成)
+    #[serde(default)]
+    pub infos: Vec<FileValidationError>,
+    /// 被拒绝的行 (同一文件的其他有效行仍会使用)
+    #[serde(default)]
+    pub rejected_rows: Vec<RejectedRow>,
+    /// 已作废或按设置排除的送货单
+    #[serde(default)]
+    pub excluded: Vec<ExcludedDocument>,
+    /// 送货单号缺号/顺序/格式问题
+    #[serde(default)]
+    pub sequence_issues: Vec<SequenceIssue>,
+    /// 跨文件内容重复的行
+    #[serde(default)]
+    pub duplicates: Vec<DuplicateGroup>,
+    /// 历史单价异常
+    #[serde(default)]
+    pub price_anomalies: Vec<PriceAnomaly>,
+    /// 日期无法识别、待指定日期的条目 (指定日期后重新扫描即可归入对应月份)
+    #[serde(default)]
+    pub unassigned: Vec<DeliveryItem>,
+    /// 与上次扫描相比新增、变化和删除的文件
+    #[serde(default)]
+    pub file_changes: FileChanges,
+    /// 任务被取消，结果只包含取消前处理完的文件
+    #[serde(default)]
+    pub cancelled: bool,
+    /// 扫描会话 ID，扫描结果保存在后端，界面按会话查询和生成
+    #[serde(default)]
+    pub session_id: String,
+    /// 每个客户每个对账单分组的汇总 (集团客户包含合并后的分组)
+    #[serde(default)]
+    pub statements: Vec<StatementSummary>,
+    /// 按货名、规格、单位汇总的销售数据 (含厚度分组，赠品不计入平均单价)
+    #[serde(default)]
+    pub products: Vec<SummaryItem>,
+}
+
+/// 客户某个对账单分组的汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatementSummary {
+    pub customer: String,
+    pub customer_type: String,
+    /// 对账单分组 (例如 2024年1月)
+    pub statement_group: String,
+    pub item_count: usize,
+    pub total_quantity: f64,
+    pub total_amount: f64,
+    /// 最近的送货日期 (用于客户排序)
+    pub last_date: String,
+    /// 是否为集团合并对账单
+    pub consolidated: bool,
+}
+
+/// 送货记录的校验状态 (有错误的行已被拒绝，不在扫描结果中)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    #[default]
+    Ok,
+    /// 已应用源数据修正
+    Corrected,
+    /// 所在行或所在文件有警告
+    Warning,
+    /// 与其他文件的行内容重复
+    Duplicate,
+}
+
+/// 查询结果的排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemSortField {
+    Date,
+    Customer,
+    CustomerType,
+    ProductName,
+    Spec,
+    Quantity,
+    Unit,
+    UnitPrice,
+    Amount,
+    DeliveryOrderNo,
+    OrderNo,
+    SourceFile,
+    StatementGroup,
+    Status,
+}
+
+/// 扫描会话中送货记录的查询条件 (文本条件为包含匹配，不区分大小写)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ItemQuery {
+    /// 客户 (集团客户包含全部子公司)，为空时不限
+    pub customer: Option<String>,
+    pub customer_type: Option<String>,
+    /// 对账单分组，为空时不限
+    pub statement_group: Option<String>,
+    /// 起始日期 (含)，日期无法识别的行不符合日期条件
+    pub date_from: Option<String>,
+    /// 截止日期 (含)
+    pub date_to: Option<String>,
+    /// 货名或规格
+    pub product: Option<String>,
+    /// 送货单号
+    pub delivery_order_no: Option<String>,
+    /// 订单号
+    pub order_no: Option<String>,
+    /// 源文件路径
+    pub source_file: Option<String>,
+    pub status: Option<ItemStatus>,
+    /// 排序字段，为空时按扫描顺序
+    pub sort_by: Option<ItemSortField>,
+    pub descending: bool,
+    pub offset: usize,
+    /// 每页条数，0 表示不分页
+    pub limit: usize,
+}
+
+/// 带校验状态的送货记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemRow {
+    #[serde(flatten)]
+    pub item: DeliveryItem,
+    pub status: ItemStatus,
+}
+
+/// 送货记录查询结果 (一页)，合计为全部符合条件的记录的合计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ItemPage {
+    pub items: Vec<ItemRow>,
+    /// 符合条件的总条数
+    pub total: usize,
+    /// 符合条件的送货单数
+    pub order_count: usize,
+    pub total_quantity: f64,
+    pub total_amount: f64,
 }
@@ -0,0 +1,196 @@
+use crate::data_processor::parse_date;
+use crate::models::{
+    AppConfig, DeliveryItem, FileValidationError, PriceListEntry, PricingBasis, PricingRule,
+};
+use crate::spec_parser::{area_m2, to_kg};
+use crate::validation::item_finding;
+
+/// 单价比较的容差
+const PRICE_EPSILON: f64 = 1e-6;
+
+/// 计价检查产生的规则 (在按行接受/拒绝之前运行，设置为错误时拒绝对应的行)
+pub const PRICING_RULES: &[&str] = &["contract_price", "zero_price", "pricing_conversion"];
+
+/// 计价流程: 价目表补价、零单价检查、计价换算
+/// 在校验中按文件运行，返回的结果与其他校验结果一起按设置决定是否拒绝行
+pub fn apply_pricing(items: &mut [DeliveryItem], config: &AppConfig) -> Vec<FileValidationError> {
+    let mut findings = apply_price_list(items, &config.price_list);
+    findings.extend(check_zero_prices(items));
+    findings.extend(apply_pricing_rules(items, &config.pricing_rules));
+    findings
+}
+
+/// 查找适用于该条目的计价规则 (按配置顺序，第一个匹配的生效)
+pub fn find_pricing_rule<'a>(item: &DeliveryItem, rules: &'a [PricingRule]) -> Option<&'a PricingRule> {
+    rules.iter().find(|rule| {
+        !rule.product_keyword.is_empty()
+            && item.product_name.contains(&rule.product_keyword)
+            && (rule.spec_keyword.is_empty() || item.spec.contains(&rule.spec_keyword))
+    })
+}
+
+/// 计价规则的换算系数 (每个送货单位折合多少计价单位)；按数量计价或规格缺少换算信息时为 None
+fn conversion_factor(item: &DeliveryItem, rule: &PricingRule) -> Option<f64> {
+    if rule.factor > 0.0 {
+        return Some(rule.factor);
+    }
+    item.spec_info.as_ref().and_then(|info| match rule.basis {
+        PricingBasis::Weight => info.pack_weight.as_ref().and_then(to_kg),
+        PricingBasis::Area => area_m2(info),
+        PricingBasis::Quantity => None,
+    })
+}
+
+/// 按计价规则换算后的计价数量 (与 `apply_pricing_rules` 的结果一致；无法换算时为送货数量)
+pub fn expected_billing_quantity(item: &DeliveryItem, rules: &[PricingRule]) -> f64 {
+    match find_pricing_rule(item, rules) {
+        Some(rule) if rule.basis != PricingBasis::Quantity => conversion_factor(item, rule)
+            .map(|factor| round_to(item.quantity * factor, 3))
+            .unwrap_or(item.quantity),
+        _ => item.quantity,
+    }
+}
+
+/// 按计价规则换算计价数量并重新计算金额
+/// 返回无法换算的条目警告 (这些条目保持按送货数量计价)
+pub fn apply_pricing_rules(
+    items: &mut [DeliveryItem],
+    rules: &[PricingRule],
+) -> Vec<FileValidationError> {
+    let mut warnings = Vec::new();
+
+    for item in items.iter_mut() {
+        item.billing_quantity = item.quantity;
+        item.billing_unit = item.unit.clone();
+
+        let rule = match find_pricing_rule(item, rules) {
+            Some(rule) if rule.basis != PricingBasis::Quantity => rule,
+            _ => continue,
+        };
+
+        let Some(factor) = conversion_factor(item, rule) else {
+            warnings.push(item_finding(
+                "pricing_conversion",
+                item,
+                format!(
+                    "无法换算计价数量: '{} {}' 的规格中缺少{}信息，已按送货数量计价",
+                    item.product_name,
+                    item.spec,
+                    if rule.basis == PricingBasis::Weight { "重量" } else { "宽度/长度" }
+                ),
+            ));
+            continue;
+        };
+
+        item.billing_quantity = round_to(item.quantity * factor, 3);
+        item.billing_unit = if rule.billing_unit.is_empty() {
+            default_billing_unit(&rule.basis).to_string()
+        } else {
+            rule.billing_unit.clone()
+        };
+
+        if item.unit_price != 0.0 {
+            item.amount = round_to(item.billing_quantity * item.unit_price, 2);
+        }
+    }
+
+    warnings
+}
+
+/// 查找条目日期生效的合同单价 (指定规格的条目优先于通用条目，同级取生效日期最新的)
+pub fn find_contract_price<'a>(
+    item: &DeliveryItem,
+    price_list: &'a [PriceListEntry],
+) -> Option<&'a PriceListEntry> {
+    let date = parse_date(&item.date).ok()?;
+
+    price_list
+        .iter()
+        .filter(|entry| {
+            entry.customer == item.customer
+                && entry.product_name == item.product_name
+                && (entry.spec.is_empty() || entry.spec == item.spec)
+        })
+        .filter(|entry| {
+            let from_ok = parse_date(&entry.effective_from).is_ok_and(|from| from <= date);
+            let to_ok = entry.effective_to.is_empty()
+                || parse_date(&entry.effective_to).is_ok_and(|to| date <= to);
+            from_ok && to_ok
+        })
+        .max_by_key(|entry| (!entry.spec.is_empty(), parse_date(&entry.effective_from).ok()))
+}
+
+/// 用价目表补录缺失的单价，并检查送货单单价与合同单价是否一致
+/// 只补录送货单上没有填写的单价；明确填写为 0 的单价保留，由零单价检查提示
+/// 返回价格偏差警告
+pub fn apply_price_list(
+    items: &mut [DeliveryItem],
+    price_list: &[PriceListEntry],
+) -> Vec<FileValidationError> {
+    let mut warnings = Vec::new();
+
+    for item in items.iter_mut() {
+        if item.is_free {
+            continue;
+        }
+        let Some(entry) = find_contract_price(item, price_list) else {
+            continue;
+        };
+
+        if item.price_missing {
+            item.unit_price = entry.unit_price;
+            item.amount = round_to(item.quantity * entry.unit_price, 2);
+            item.price_filled = true;
+        } else if (item.unit_price - entry.unit_price).abs() > PRICE_EPSILON {
+            warnings.push(item_finding(
+                "contract_price",
+                item,
+                format!(
+                    "单价与合同价不符: 送货单号 '{}' 的 '{} {}' 单价 {}，合同价 {} (自 {} 起)",
+                    item.delivery_order_no,
+                    item.product_name,
+                    item.spec,
+                    item.unit_price,
+                    entry.unit_price,
+                    entry.effective_from
+                ),
+            ));
+        }
+    }
+
+    warnings
+}
+
+/// 检查未说明原因的零单价 (非赠品且价目表也未补录)
+pub fn check_zero_prices(items: &[DeliveryItem]) -> Vec<FileValidationError> {
+    items
+        .iter()
+        .filter(|item| !item.is_free && item.unit_price == 0.0)
+        .map(|item| {
+            item_finding(
+                "zero_price",
+                item,
+                format!(
+                    "{}: 送货单号 '{}' 的 '{} {}'，如为赠品请在货名或备注中注明",
+                    if item.price_missing { "缺少单价" } else { "单价为 0" },
+                    item.delivery_order_no,
+                    item.product_name,
+                    item.spec
+                ),
+            )
+        })
+        .collect()
+}
+
+fn default_billing_unit(basis: &PricingBasis) -> &'static str {
+    match basis {
+        PricingBasis::Weight => "kg",
+        PricingBasis::Area => "㎡",
+        PricingBasis::Quantity => "",
+    }
+}
+
+fn round_to(value: f64, digits: i32) -> f64 {
+    let scale = 10f64.powi(digits);
+    (value * scale).round() / scale
+}
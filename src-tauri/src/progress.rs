@@ -0,0 +1,122 @@
+use crate::models::ProgressInfo;
+use crate::workers::Progress;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::Emitter;
+
+/// 当前阶段
+struct Phase {
+    step: String,
+    total: usize,
+    started: Instant,
+}
+
+/// 通过 "progress" 事件向界面报告各阶段进度 (数量、耗时和预计剩余时间)
+pub struct ProgressReporter {
+    app: tauri::AppHandle,
+    phase: Mutex<Phase>,
+    done: AtomicUsize,
+    cancelled: Arc<AtomicBool>,
+    finished: AtomicBool,
+}
+
+impl ProgressReporter {
+    pub fn new(app: &tauri::AppHandle, cancelled: Arc<AtomicBool>) -> Self {
+        Self {
+            app: app.clone(),
+            cancelled,
+            phase: Mutex::new(Phase {
+                step: String::new(),
+                total: 0,
+                started: Instant::now(),
+            }),
+            done: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// 发送 "done" 事件，界面据此清除进度条；只发送一次，提前返回时由 Drop 补发
+    pub fn finish(&self) {
+        if self.finished.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let phase = Phase {
+            step: "done".to_string(),
+            total: 0,
+            started: Instant::now(),
+        };
+        self.emit(&phase, 0, "", "");
+    }
+
+    fn emit(&self, phase: &Phase, current: usize, file: &str, status: &str) {
+        let elapsed = phase.started.elapsed().as_secs_f64();
+        let eta_seconds = (current > 0 && phase.total > 0)
+            .then(|| elapsed / current as f64 * phase.total.saturating_sub(current) as f64);
+
+        let name = Path::new(file)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| file.to_string());
+        let message = if phase.total > 0 {
+            format!("{} {}/{} {}", step_label(&phase.step), current, phase.total, name)
+        } else {
+            format!("{}...", step_label(&phase.step))
+        };
+
+        let _ = self.app.emit(
+            "progress",
+            ProgressInfo {
+                step: phase.step.clone(),
+                current,
+                total: phase.total,
+                message,
+                file: file.to_string(),
+                status: status.to_string(),
+                elapsed_seconds: elapsed,
+                eta_seconds,
+            },
+        );
+    }
+}
+
+impl Progress for ProgressReporter {
+    fn start(&self, step: &str, total: usize) {
+        let mut phase = self.phase.lock().unwrap();
+        *phase = Phase {
+            step: step.to_string(),
+            total,
+            started: Instant::now(),
+        };
+        self.done.store(0, Ordering::Relaxed);
+        self.emit(&phase, 0, "", "");
+    }
+
+    fn advance(&self, file: &str, status: &str) {
+        let phase = self.phase.lock().unwrap();
+        let current = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.emit(&phase, current, file, status);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+fn step_label(step: &str) -> &str {
+    match step {
+        "scan" => "扫描文件",
+        "parse" => "解析",
+        "validate" => "校验",
+        "generate" => "生成对账单",
+        "done" => "完成",
+        other => other,
+    }
+}
@@ -0,0 +1,356 @@
+use crate::corrections::file_fingerprint;
+use crate::error::AppError;
+use crate::excel_parser::extract_delivery_data;
+use crate::models::{DeliveryItem, FileChanges};
+use crate::workers::{run_parallel, Progress};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// 解析结果格式版本，解析逻辑变化时递增以让旧缓存失效
+const PARSER_VERSION: i64 = 5;
+
+/// 解析缓存表 (随台账数据库在启动时创建)
+pub const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS parse_cache (
+    path          TEXT PRIMARY KEY,
+    size          INTEGER NOT NULL,
+    mtime         INTEGER NOT NULL,
+    fingerprint   TEXT NOT NULL,
+    customer_type TEXT NOT NULL,
+    version       INTEGER NOT NULL,
+    items         TEXT NOT NULL,
+    error         TEXT
+);
+";
+
+/// 缓存的单个文件解析结果
+#[derive(Clone)]
+struct CacheEntry {
+    size: i64,
+    mtime: i64,
+    fingerprint: String,
+    customer_type: String,
+    items: Vec<DeliveryItem>,
+    error: Option<AppError>,
+}
+
+/// 缓存中保存的解析错误 (以 JSON 存入 error 列)
+/// 解析只产生工作簿错误，保留文件、工作表和单元格，读取缓存时还原为同样的 `AppError`
+#[derive(Serialize, Deserialize)]
+struct StoredError {
+    file: String,
+    sheet: Option<String>,
+    cell: Option<String>,
+    reason: String,
+}
+
+impl From<&AppError> for StoredError {
+    fn from(error: &AppError) -> Self {
+        match error {
+            AppError::Workbook {
+                file,
+                sheet,
+                cell,
+                reason,
+            } => StoredError {
+                file: file.clone(),
+                sheet: sheet.clone(),
+                cell: cell.clone(),
+                reason: reason.clone(),
+            },
+            other => StoredError {
+                file: other.file().unwrap_or_default().to_string(),
+                sheet: None,
+                cell: None,
+                reason: other.to_string(),
+            },
+        }
+    }
+}
+
+impl From<StoredError> for AppError {
+    fn from(error: StoredError) -> Self {
+        AppError::Workbook {
+            file: error.file,
+            sheet: error.sheet,
+            cell: error.cell,
+            reason: error.reason,
+        }
+    }
+}
+
+/// 文件解析结果 (来自缓存或重新解析)
+pub struct ParsedFile {
+    /// 文件内容指纹 (读取失败时为空)
+    pub fingerprint: String,
+    pub items: Result<Vec<DeliveryItem>, AppError>,
+}
+
+/// 文件解析缓存: 按 路径 + 大小 + 修改时间 + 内容指纹 复用上次的解析结果，只解析新增或变化的文件
+pub struct ScanCache {
+    entries: HashMap<String, CacheEntry>,
+    seen: HashSet<String>,
+    /// 本次确认已删除的文件
+    removed: Vec<String>,
+    changes: FileChanges,
+}
+
+impl ScanCache {
+    /// 从台账数据库加载缓存
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let mut stmt = conn.prepare(
+            "SELECT path, size, mtime, fingerprint, customer_type, items, error
+             FROM parse_cache WHERE version = ?1",
+        )?;
+        let rows = stmt.query_map(params![PARSER_VERSION], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, Option<String>>(6)?,
+            ))
+        })?;
+
+        let mut entries = HashMap::new();
+        for row in rows {
+            let (path, size, mtime, fingerprint, customer_type, items, error) = row?;
+            // 无法读取的缓存条目丢弃，下次重新解析
+            let Ok(items) = serde_json::from_str(&items) else {
+                continue;
+            };
+            let error = match error.map(|e| serde_json::from_str::<StoredError>(&e)) {
+                None => None,
+                Some(Ok(stored)) => Some(AppError::from(stored)),
+                Some(Err(_)) => continue,
+            };
+            entries.insert(
+                path,
+                CacheEntry {
+                    size,
+                    mtime,
+                    fingerprint,
+                    customer_type,
+                    items,
+                    error,
+                },
+            );
+        }
+
+        Ok(Self {
+            entries,
+            seen: HashSet::new(),
+            removed: Vec::new(),
+            changes: FileChanges::default(),
+        })
+    }
+
+    /// 空缓存 (台账不可用时每次都重新解析)
+    pub fn empty() -> Self {
+        Self {
+            entries: HashMap::new(),
+            seen: HashSet::new(),
+            removed: Vec::new(),
+            changes: FileChanges::default(),
+        }
+    }
+
+    /// 并行解析文件，未变化的文件直接使用缓存；结果顺序与输入顺序一致
+    /// 任务取消后未开始解析的文件结果为 None
+    pub fn parse_all(
+        &mut self,
+        files: &[(PathBuf, String)],
+        workers: usize,
+        progress: &dyn Progress,
+    ) -> Vec<Option<ParsedFile>> {
+        let jobs: Vec<(&Path, &str, Option<&CacheEntry>)> = files
+            .iter()
+            .map(|(file, customer_type)| {
+                let cached = self
+                    .entries
+                    .get(file.to_string_lossy().as_ref())
+                    .filter(|e| e.customer_type == *customer_type);
+                (file.as_path(), customer_type.as_str(), cached)
+            })
+            .collect();
+
+        progress.start("parse", jobs.len());
+        let results = run_parallel(&jobs, workers, |(file, customer_type, cached)| {
+            if progress.is_cancelled() {
+                return None;
+            }
+            let (entry, reparsed) = load_entry(file, customer_type, *cached);
+            let status = match (&entry.error, reparsed) {
+                (Some(_), _) => "error",
+                (None, true) => "parsed",
+                (None, false) => "cached",
+            };
+            progress.advance(&file.to_string_lossy(), status);
+            Some((entry, reparsed))
+        });
+
+        files
+            .iter()
+            .zip(results)
+            .map(|((file, _), result)| {
+                let (entry, reparsed) = result?;
+                let path = file.to_string_lossy().to_string();
+                if reparsed {
+                    if self.entries.contains_key(&path) {
+                        self.changes.changed.push(path.clone());
+                    } else {
+                        self.changes.added.push(path.clone());
+                    }
+                }
+                let parsed = to_parsed(&entry);
+                self.seen.insert(path.clone());
+                self.entries.insert(path, entry);
+                Some(parsed)
+            })
+            .collect()
+    }
+
+    /// 结束本次扫描: 未出现的文件记为已删除，返回与上次扫描相比的文件变化
+    /// `scanned_files` 为本次扫描到但未解析的文件 (例如已作废或按设置排除)，这些文件不算删除
+    pub fn finish(&mut self, scanned_files: &[String]) -> FileChanges {
+        self.seen.extend(scanned_files.iter().cloned());
+
+        let mut removed: Vec<String> = self
+            .entries
+            .keys()
+            .filter(|path| !self.seen.contains(*path))
+            .cloned()
+            .collect();
+        removed.sort();
+        for path in &removed {
+            self.entries.remove(path);
+        }
+        self.removed.extend(removed.iter().cloned());
+
+        let mut changes = std::mem::take(&mut self.changes);
+        changes.removed = removed;
+        changes.added.sort();
+        changes.changed.sort();
+        changes
+    }
+
+    /// 结束只处理部分文件的扫描 (例如自动导入): 只移除指定的已删除文件，返回文件变化
+    pub fn finish_partial(&mut self, removed: &[String]) -> FileChanges {
+        for path in removed {
+            self.entries.remove(path);
+        }
+        self.removed.extend(removed.iter().cloned());
+        let mut changes = std::mem::take(&mut self.changes);
+        changes.removed = removed.to_vec();
+        changes
+    }
+
+    /// 保存缓存到台账数据库: 只写入本次处理过的文件、删除本次确认已删除的文件，
+    /// 不影响同时运行的其他扫描写入的条目
+    pub fn save(&self, conn: &mut Connection) -> Result<()> {
+        let tx = conn.transaction().context("开始缓存事务失败")?;
+        tx.execute("DELETE FROM parse_cache WHERE version <> ?1", params![PARSER_VERSION])?;
+        for path in &self.removed {
+            tx.execute("DELETE FROM parse_cache WHERE path = ?1", params![path])?;
+        }
+        for (path, entry) in self.entries.iter().filter(|(path, _)| self.seen.contains(*path)) {
+            let items = serde_json::to_string(&entry.items).context("序列化解析结果失败")?;
+            let error = entry
+                .error
+                .as_ref()
+                .map(|e| serde_json::to_string(&StoredError::from(e)))
+                .transpose()
+                .context("序列化解析错误失败")?;
+            tx.execute(
+                "INSERT OR REPLACE INTO parse_cache (path, size, mtime, fingerprint, customer_type, version, items, error)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    path,
+                    entry.size,
+                    entry.mtime,
+                    entry.fingerprint,
+                    entry.customer_type,
+                    PARSER_VERSION,
+                    items,
+                    error,
+                ],
+            )?;
+        }
+        tx.commit().context("保存解析缓存失败")?;
+        Ok(())
+    }
+}
+
+/// 查询缓存中的文件指纹 (用于台账记录来源，避免重复读取文件)
+pub fn cached_fingerprint(conn: &Connection, path: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT fingerprint FROM parse_cache WHERE path = ?1",
+        params![path],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+/// 读取单个文件: 未变化时沿用缓存，否则重新解析；返回缓存条目和是否重新解析
+fn load_entry(file: &Path, customer_type: &str, cached: Option<&CacheEntry>) -> (CacheEntry, bool) {
+    let (size, mtime) = file_stat(file);
+
+    // 大小和修改时间都没变，不读取文件
+    if let Some(entry) = cached.filter(|e| e.size == size && e.mtime == mtime) {
+        return (entry.clone(), false);
+    }
+
+    let fingerprint = file_fingerprint(file).unwrap_or_default();
+
+    // 修改时间变了但内容相同 (例如复制或打开后另存)，沿用解析结果
+    if let Some(entry) = cached.filter(|e| !fingerprint.is_empty() && e.fingerprint == fingerprint) {
+        let mut entry = entry.clone();
+        entry.size = size;
+        entry.mtime = mtime;
+        return (entry, false);
+    }
+
+    let (items, error) = match extract_delivery_data(file, customer_type) {
+        Ok(items) => (items, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    let entry = CacheEntry {
+        size,
+        mtime,
+        fingerprint,
+        customer_type: customer_type.to_string(),
+        items,
+        error,
+    };
+    (entry, true)
+}
+
+fn to_parsed(entry: &CacheEntry) -> ParsedFile {
+    ParsedFile {
+        fingerprint: entry.fingerprint.clone(),
+        items: match &entry.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(entry.items.clone()),
+        },
+    }
+}
+
+/// 文件大小和修改时间 (纳秒)，读取失败时为 -1
+fn file_stat(file: &Path) -> (i64, i64) {
+    let Ok(meta) = std::fs::metadata(file) else {
+        return (-1, -1);
+    };
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(-1);
+    (meta.len() as i64, mtime)
+}
@@ -0,0 +1,255 @@
+use crate::data_processor::parse_date;
+use crate::models::{DeliveryItem, FileValidationError, OrderNoBook, SequenceIssue, SequenceIssueKind};
+use crate::validation::finding;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+/// 超过该跳号数量时视为换了新单本，只提示一次而不逐个列出缺号
+const MAX_LISTED_GAP: u64 = 50;
+
+/// 编译各客户的单号格式 (每次扫描编译一次)，返回有效的格式和无效格式的提示
+pub fn compile_order_formats(
+    books: &[OrderNoBook],
+) -> (Vec<(&OrderNoBook, Regex)>, Vec<FileValidationError>) {
+    let mut formats = Vec::new();
+    let mut invalid = Vec::new();
+    for book in books {
+        match Regex::new(&book.pattern) {
+            Ok(re) => formats.push((book, re)),
+            Err(e) => invalid.push(finding(
+                "invalid_pattern",
+                "",
+                format!("单号格式 '{}' 不是有效的正则表达式，已忽略: {}", book.pattern, e),
+            )),
+        }
+    }
+    (formats, invalid)
+}
+
+/// 检查送货单号的缺号、日期顺序和格式 (`patterns` 由 `compile_order_formats` 编译)
+pub fn check_order_sequences(
+    items: &[DeliveryItem],
+    patterns: &[(&OrderNoBook, Regex)],
+) -> Vec<SequenceIssue> {
+    let mut issues = Vec::new();
+
+    // (客户, 单本) -> 流水号 -> (单号, 日期, 文件)
+    type Sequence<'a> = BTreeMap<u64, (&'a str, &'a str, &'a str)>;
+    let mut sequences: HashMap<(&str, String), Sequence> = HashMap::new();
+
+    for item in items {
+        if item.delivery_order_no.is_empty() {
+            continue;
+        }
+
+        // 指定客户的格式优先于通用格式
+        let pattern = patterns
+            .iter()
+            .filter(|(b, _)| b.customer.is_empty() || b.customer == item.customer)
+            .max_by_key(|(b, _)| !b.customer.is_empty());
+
+        let parsed = match pattern {
+            Some((_, re)) => match parse_with_pattern(re, &item.delivery_order_no) {
+                Some(parsed) => Some(parsed),
+                None => {
+                    issues.push(SequenceIssue {
+                        customer: item.customer.clone(),
+                        book: String::new(),
+                        kind: SequenceIssueKind::Malformed,
+                        delivery_order_no: item.delivery_order_no.clone(),
+                        file: item.source_file.clone(),
+                        message: format!("单号 '{}' 不符合格式 '{}'", item.delivery_order_no, re.as_str()),
+                    });
+                    None
+                }
+            },
+            None => split_trailing_number(&item.delivery_order_no),
+        };
+
+        if let Some((book, seq)) = parsed {
+            sequences
+                .entry((&item.customer, book))
+                .or_default()
+                .entry(seq)
+                .or_insert((&item.delivery_order_no, &item.date, &item.source_file));
+        }
+    }
+
+    for ((customer, book), seq_map) in &sequences {
+        let entries: Vec<(&u64, &(&str, &str, &str))> = seq_map.iter().collect();
+
+        for pair in entries.windows(2) {
+            let (prev_seq, (prev_no, prev_date, _)) = pair[0];
+            let (seq, (no, date, file)) = pair[1];
+
+            // 缺号
+            let gap = seq - prev_seq - 1;
+            if gap > 0 {
+                let message = if gap > MAX_LISTED_GAP {
+                    format!("单号从 '{}' 跳到 '{}'，中间缺 {} 个号，可能换了新单本", prev_no, no, gap)
+                } else if gap == 1 {
+                    format!("缺少单号: '{}' 与 '{}' 之间缺 1 个号 ({})", prev_no, no, prev_seq + 1)
+                } else {
+                    format!(
+                        "缺少单号: '{}' 与 '{}' 之间缺 {} 个号 ({}-{})",
+                        prev_no, no, gap, prev_seq + 1, seq - 1
+                    )
+                };
+                issues.push(SequenceIssue {
+                    customer: customer.to_string(),
+                    book: book.clone(),
+                    kind: SequenceIssueKind::Gap,
+                    delivery_order_no: no.to_string(),
+                    file: file.to_string(),
+                    message,
+                });
+            }
+
+            // 单号递增但日期倒退
+            if let (Ok(d1), Ok(d2)) = (parse_date(prev_date), parse_date(date)) {
+                if d2 < d1 {
+                    issues.push(SequenceIssue {
+                        customer: customer.to_string(),
+                        book: book.clone(),
+                        kind: SequenceIssueKind::OutOfOrder,
+                        delivery_order_no: no.to_string(),
+                        file: file.to_string(),
+                        message: format!(
+                            "日期顺序异常: 单号 '{}' ({}) 早于前一单号 '{}' ({})",
+                            no, d2, prev_no, d1
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    issues.sort_by(|a, b| {
+        a.customer
+            .cmp(&b.customer)
+            .then(a.book.cmp(&b.book))
+            .then(a.delivery_order_no.cmp(&b.delivery_order_no))
+    });
+    issues
+}
+
+/// 按配置的正则拆分单本和流水号 (命名组 book / seq，没有 seq 组时取最后一段数字)
+fn parse_with_pattern(re: &Regex, no: &str) -> Option<(String, u64)> {
+    let caps = re.captures(no)?;
+    // 要求整个单号都符合格式
+    if caps.get(0)?.as_str() != no {
+        return None;
+    }
+    let book = caps.name("book").map(|m| m.as_str().to_string()).unwrap_or_default();
+    match caps.name("seq") {
+        Some(seq) => seq.as_str().parse().ok().map(|seq| (book, seq)),
+        None => split_trailing_number(no).map(|(_, seq)| (book, seq)),
+    }
+}
+
+/// 默认规则: 末尾连续数字为流水号，前面部分为单本
+fn split_trailing_number(no: &str) -> Option<(String, u64)> {
+    let digits_start = no
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_ascii_digit())
+        .last()
+        .map(|(i, _)| i)?;
+    let seq = no[digits_start..].parse().ok()?;
+    Some((no[..digits_start].to_string(), seq))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(delivery_order_no: &str, date: &str) -> DeliveryItem {
+        DeliveryItem {
+            product_name: "PE膜".to_string(),
+            quantity: 1.0,
+            unit: "卷".to_string(),
+            customer: "客户甲".to_string(),
+            date: date.to_string(),
+            delivery_order_no: delivery_order_no.to_string(),
+            source_file: format!("{}.xlsx", delivery_order_no),
+            source_row: 9,
+            billing_quantity: 1.0,
+            billing_unit: "卷".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn book(customer: &str, pattern: &str) -> OrderNoBook {
+        OrderNoBook {
+            customer: customer.to_string(),
+            pattern: pattern.to_string(),
+        }
+    }
+
+    #[test]
+    fn reports_gaps() {
+        let items = [
+            item("A001", "2024-01-01"),
+            item("A002", "2024-01-02"),
+            item("A005", "2024-01-03"),
+        ];
+        let issues = check_order_sequences(&items, &[]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, SequenceIssueKind::Gap);
+        assert_eq!(issues[0].book, "A");
+        assert_eq!(issues[0].delivery_order_no, "A005");
+        assert!(issues[0].message.contains("缺 2 个号"));
+    }
+
+    #[test]
+    fn large_gap_is_reported_once() {
+        let items = [item("A001", "2024-01-01"), item("A100", "2024-01-02")];
+        let issues = check_order_sequences(&items, &[]);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("可能换了新单本"));
+    }
+
+    #[test]
+    fn reports_dates_out_of_order() {
+        let items = [item("A001", "2024-01-05"), item("A002", "2024-01-03")];
+        let issues = check_order_sequences(&items, &[]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, SequenceIssueKind::OutOfOrder);
+        assert_eq!(issues[0].delivery_order_no, "A002");
+    }
+
+    #[test]
+    fn books_are_checked_separately() {
+        let items = [item("A001", "2024-01-01"), item("B002", "2024-01-02")];
+        assert!(check_order_sequences(&items, &[]).is_empty());
+    }
+
+    #[test]
+    fn applies_configured_format() {
+        let books = [book("客户甲", r"(?P<book>[A-Z])-(?P<seq>\d{3})")];
+        let (formats, invalid) = compile_order_formats(&books);
+        assert!(invalid.is_empty());
+
+        let items = [
+            item("A-001", "2024-01-01"),
+            item("A-003", "2024-01-02"),
+            item("A3", "2024-01-02"),
+        ];
+        let issues = check_order_sequences(&items, &formats);
+        let kinds: Vec<_> = issues.iter().map(|i| (i.kind.clone(), i.delivery_order_no.as_str())).collect();
+        assert_eq!(
+            kinds,
+            vec![(SequenceIssueKind::Malformed, "A3"), (SequenceIssueKind::Gap, "A-003")]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_formats() {
+        let books = [book("", "(unclosed"), book("", r"\d+")];
+        let (formats, invalid) = compile_order_formats(&books);
+        assert_eq!(formats.len(), 1);
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].rule_id, "invalid_pattern");
+        assert!(invalid[0].error.contains("(unclosed"));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::data_processor::parse_date;
+use crate::duplicates::is_duplicate_line;
+use crate::models::{
+    CustomerGroup, DeliveryItem, DuplicateGroup, FileValidationError, ItemPage, ItemQuery, ItemRow,
+    ItemSortField, ItemStatus, StatementSummary,
+};
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 最多保留的扫描会话数，超出时丢弃最早的会话
+const MAX_SESSIONS: usize = 4;
+
+/// 一次扫描的结果 (保存在后端，界面按会话 ID 查询)
+pub struct ScanSession {
+    /// 已标注对账单分组的送货记录
+    items: Vec<DeliveryItem>,
+    /// 每条记录的校验状态 (与 items 一一对应)
+    statuses: Vec<ItemStatus>,
+    /// 扫描时的集团客户设置
+    customer_groups: Vec<CustomerGroup>,
+}
+
+impl ScanSession {
+    /// 创建会话，按警告和跨文件重复标注每条记录的校验状态
+    pub fn new(
+        items: Vec<DeliveryItem>,
+        customer_groups: Vec<CustomerGroup>,
+        warnings: &[FileValidationError],
+        duplicates: &[DuplicateGroup],
+    ) -> Self {
+        let warned_rows: HashSet<(&str, usize)> = warnings
+            .iter()
+            .filter_map(|w| w.row.map(|row| (w.file.as_str(), row)))
+            .collect();
+        let warned_files: HashSet<&str> = warnings
+            .iter()
+            .filter(|w| w.row.is_none())
+            .map(|w| w.file.as_str())
+            .collect();
+
+        let statuses = items
+            .iter()
+            .map(|item| {
+                if is_duplicate_line(item, duplicates) {
+                    ItemStatus::Duplicate
+                } else if warned_files.contains(item.source_file.as_str())
+                    || warned_rows.contains(&(item.source_file.as_str(), item.source_row))
+                {
+                    ItemStatus::Warning
+                } else if !item.corrections.is_empty() {
+                    ItemStatus::Corrected
+                } else {
+                    ItemStatus::Ok
+                }
+            })
+            .collect();
+
+        Self {
+            items,
+            statuses,
+            customer_groups,
+        }
+    }
+
+    /// 客户的送货记录；集团客户包含全部子公司的记录
+    fn matches_customer(&self, item: &DeliveryItem, customer: &str) -> bool {
+        item.customer == customer
+            || self
+                .customer_groups
+                .iter()
+                .any(|g| g.parent == customer && g.children.contains(&item.customer))
+    }
+
+    /// 按条件查询送货记录 (排序、分页)，同时返回全部符合条件的记录的合计
+    pub fn query(&self, query: &ItemQuery) -> ItemPage {
+        let date_from = query.date_from.as_deref().and_then(|d| parse_date(d).ok());
+        let date_to = query.date_to.as_deref().and_then(|d| parse_date(d).ok());
+        let product = query.product.as_deref().map(str::to_lowercase);
+
+        let mut matched: Vec<(&DeliveryItem, ItemStatus)> = self
+            .items
+            .iter()
+            .zip(self.statuses.iter().copied())
+            .filter(|(item, status)| {
+                query.customer.as_deref().is_none_or(|c| self.matches_customer(item, c))
+                    && query.customer_type.as_deref().is_none_or(|t| item.customer_type == t)
+                    && query.statement_group.as_deref().is_none_or(|g| item.statement_group == g)
+                    && query.status.is_none_or(|s| *status == s)
+                    && product.as_deref().is_none_or(|p| {
+                        item.product_name.to_lowercase().contains(p) || item.spec.to_lowercase().contains(p)
+                    })
+                    && contains(&item.delivery_order_no, query.delivery_order_no.as_deref())
+                    && contains(&item.order_no, query.order_no.as_deref())
+                    && contains(&item.source_file, query.source_file.as_deref())
+            })
+            .filter(|(item, _)| {
+                if date_from.is_none() && date_to.is_none() {
+                    return true;
+                }
+                let Ok(date) = parse_date(&item.date) else {
+                    return false;
+                };
+                date_from.is_none_or(|from| date >= from) && date_to.is_none_or(|to| date <= to)
+            })
+            .collect();
+
+        let mut orders: HashSet<(&str, &str)> = HashSet::new();
+        let mut page = ItemPage {
+            total: matched.len(),
+            ..Default::default()
+        };
+        for (item, _) in &matched {
+            orders.insert((item.source_file.as_str(), item.delivery_order_no.as_str()));
+            page.total_quantity += item.quantity;
+            page.total_amount += item.amount;
+        }
+        page.order_count = orders.len();
+
+        if let Some(field) = query.sort_by {
+            // 稳定排序，相同值保持扫描顺序
+            matched.sort_by(|a, b| {
+                let ordering = compare_by(field, a, b);
+                if query.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+
+        let limit = if query.limit == 0 { matched.len() } else { query.limit };
+        page.items = matched
+            .into_iter()
+            .skip(query.offset)
+            .take(limit)
+            .map(|(item, status)| ItemRow {
+                item: item.clone(),
+                status,
+            })
+            .collect();
+        page
+    }
+
+    /// 某个客户某个对账单分组的全部送货记录 (用于生成对账单)
+    pub fn statement_items(&self, customer: &str, statement_group: &str) -> Vec<DeliveryItem> {
+        self.query(&ItemQuery {
+            customer: Some(customer.to_string()),
+            statement_group: Some(statement_group.to_string()),
+            ..Default::default()
+        })
+        .items
+        .into_iter()
+        .map(|row| row.item)
+        .collect()
+    }
+
+    /// 按客户和对账单分组汇总 (集团客户额外汇总子公司的合计)
+    pub fn summarize(&self) -> Vec<StatementSummary> {
+        let mut summaries: BTreeMap<(String, String), StatementSummary> = BTreeMap::new();
+
+        for item in self.items.iter().filter(|i| !i.customer.is_empty()) {
+            let parents = self
+                .customer_groups
+                .iter()
+                .filter(|g| g.children.contains(&item.customer))
+                .map(|g| (g.parent.as_str(), true));
+
+            for (customer, consolidated) in std::iter::once((item.customer.as_str(), false)).chain(parents) {
+                let summary = summaries
+                    .entry((customer.to_string(), item.statement_group.clone()))
+                    .or_insert_with(|| StatementSummary {
+                        customer: customer.to_string(),
+                        customer_type: item.customer_type.clone(),
+                        statement_group: item.statement_group.clone(),
+                        consolidated,
+                        ..Default::default()
+                    });
+                summary.item_count += 1;
+                summary.total_quantity += item.quantity;
+                summary.total_amount += item.amount;
+                if item.date > summary.last_date {
+                    summary.last_date = item.date.clone();
+                }
+            }
+        }
+
+        summaries.into_values().collect()
+    }
+}
+
+/// 文本包含匹配 (不区分大小写)，条件为空时不限
+fn contains(value: &str, pattern: Option<&str>) -> bool {
+    pattern.is_none_or(|p| value.to_lowercase().contains(&p.to_lowercase()))
+}
+
+/// 按字段比较两条记录；日期按实际日期比较，无法识别的日期排在最前
+fn compare_by(
+    field: ItemSortField,
+    (a, a_status): &(&DeliveryItem, ItemStatus),
+    (b, b_status): &(&DeliveryItem, ItemStatus),
+) -> CmpOrdering {
+    match field {
+        ItemSortField::Date => parse_date(&a.date)
+            .ok()
+            .cmp(&parse_date(&b.date).ok())
+            .then_with(|| a.date.cmp(&b.date)),
+        ItemSortField::Customer => a.customer.cmp(&b.customer),
+        ItemSortField::CustomerType => a.customer_type.cmp(&b.customer_type),
+        ItemSortField::ProductName => a.product_name.cmp(&b.product_name),
+        ItemSortField::Spec => a.spec.cmp(&b.spec),
+        ItemSortField::Quantity => a.quantity.total_cmp(&b.quantity),
+        ItemSortField::Unit => a.unit.cmp(&b.unit),
+        ItemSortField::UnitPrice => a.unit_price.total_cmp(&b.unit_price),
+        ItemSortField::Amount => a.amount.total_cmp(&b.amount),
+        ItemSortField::DeliveryOrderNo => a.delivery_order_no.cmp(&b.delivery_order_no),
+        ItemSortField::OrderNo => a.order_no.cmp(&b.order_no),
+        ItemSortField::SourceFile => a
+            .source_file
+            .cmp(&b.source_file)
+            .then(a.source_row.cmp(&b.source_row)),
+        ItemSortField::StatementGroup => a.statement_group.cmp(&b.statement_group),
+        ItemSortField::Status => (*a_status as u8).cmp(&(*b_status as u8)),
+    }
+}
+
+/// 扫描会话 (Tauri 托管状态)
+#[derive(Default)]
+pub struct SessionStore {
+    sessions: Mutex<VecDeque<(String, Arc<ScanSession>)>>,
+    next_id: AtomicU64,
+}
+
+impl SessionStore {
+    /// 保存扫描结果，返回会话 ID
+    pub fn insert(&self, session: ScanSession) -> String {
+        let id = format!(
+            "{}-{}",
+            chrono::Local::now().format("%Y%m%d%H%M%S"),
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.push_back((id.clone(), Arc::new(session)));
+        while sessions.len() > MAX_SESSIONS {
+            sessions.pop_front();
+        }
+        id
+    }
+
+    /// 按 ID 获取会话 (已被丢弃时返回 None)
+    pub fn get(&self, session_id: &str) -> Option<Arc<ScanSession>> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(id, _)| id == session_id)
+            .map(|(_, session)| session.clone())
+    }
+}
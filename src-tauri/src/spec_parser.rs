@@ -0,0 +1,215 @@
+use crate::models::{SpecInfo, SpecValue};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// 数字及其单位
+static VALUE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(\d+(?:\.\d+)?)\s*(mm|cm|um|μm|kg|m|g|毫米|厘米|微米|丝|米|公斤|千克|克|斤)?").unwrap()
+});
+
+/// 多维规格中的乘号分隔 (例如 "160*1000")
+static DIMENSIONS_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d\s*[*×xX]\s*\d").unwrap());
+
+/// 解析规格字符串，提取宽度、长度、厚度、包装重量
+/// 支持的写法: "160*1000米"、"0.05mm×500mm"、"25kg/包"、"3丝*600*500m"
+pub fn parse_spec(spec: &str) -> Option<SpecInfo> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return None;
+    }
+
+    let mut info = SpecInfo::default();
+
+    // 1. 包装重量: "25kg/包"、"20公斤/卷"
+    let mut dims: Vec<(f64, Option<String>)> = Vec::new();
+    for caps in VALUE_RE.captures_iter(spec) {
+        let value = caps[1].parse::<f64>().ok()?;
+        let unit = caps.get(2).map(|m| normalize_unit(m.as_str()));
+        match unit.as_deref() {
+            Some("kg") | Some("g") | Some("斤") => {
+                if info.pack_weight.is_none() {
+                    info.pack_weight = Some(SpecValue {
+                        value,
+                        unit: unit.unwrap(),
+                    });
+                    info.pack_unit = extract_pack_unit(spec, caps.get(0).unwrap().end());
+                }
+            }
+            _ => dims.push((value, unit)),
+        }
+    }
+
+    // 只有一个无单位的数字时无法判断含义 (例如 "A4"、"3号")
+    let has_separator = spec.contains(['*', '×', 'x', 'X']);
+    if dims.len() == 1 && dims[0].1.is_none() && !has_separator {
+        dims.clear();
+    }
+
+    // 2. 补齐尺寸单位: 无单位的数字沿用后一个 mm/cm 单位，否则视为 mm
+    let mut units: Vec<String> = Vec::with_capacity(dims.len());
+    for idx in 0..dims.len() {
+        let unit = match &dims[idx].1 {
+            Some(u) => u.clone(),
+            None => dims[idx + 1..]
+                .iter()
+                .find_map(|(_, u)| u.clone())
+                .filter(|u| u == "mm" || u == "cm")
+                .unwrap_or_else(|| "mm".to_string()),
+        };
+        units.push(unit);
+    }
+
+    // 3. 按单位和大小归类
+    for ((value, _), unit) in dims.iter().zip(units) {
+        let dim = SpecValue { value: *value, unit };
+        let is_thickness = dim.unit == "um"
+            || dim.unit == "丝"
+            || (info.thickness.is_none() && to_mm(&dim).is_some_and(|mm| mm < 1.0));
+
+        if dim.unit == "m" {
+            if info.length.is_none() {
+                info.length = Some(dim);
+            }
+        } else if is_thickness && info.thickness.is_none() {
+            info.thickness = Some(dim);
+        } else if info.width.is_none() {
+            info.width = Some(dim);
+        } else if info.length.is_none() {
+            info.length = Some(dim);
+        }
+    }
+
+    if info.width.is_none()
+        && info.length.is_none()
+        && info.thickness.is_none()
+        && info.pack_weight.is_none()
+    {
+        return None;
+    }
+
+    Some(info)
+}
+
+/// 判断字符串是否为多维规格 (例如 "160*1000米")，这类内容不能当作单个数字
+pub fn looks_like_dimensions(s: &str) -> bool {
+    DIMENSIONS_RE.is_match(s)
+}
+
+/// 将长度类规格值换算为毫米
+pub fn to_mm(v: &SpecValue) -> Option<f64> {
+    match v.unit.as_str() {
+        "mm" => Some(v.value),
+        "cm" => Some(v.value * 10.0),
+        "m" => Some(v.value * 1000.0),
+        "um" => Some(v.value / 1000.0),
+        "丝" => Some(v.value / 100.0),
+        _ => None,
+    }
+}
+
+/// 将长度类规格值换算为米
+pub fn to_m(v: &SpecValue) -> Option<f64> {
+    to_mm(v).map(|mm| mm / 1000.0)
+}
+
+/// 将重量类规格值换算为千克
+pub fn to_kg(v: &SpecValue) -> Option<f64> {
+    match v.unit.as_str() {
+        "kg" => Some(v.value),
+        "g" => Some(v.value / 1000.0),
+        "斤" => Some(v.value / 2.0),
+        _ => None,
+    }
+}
+
+/// 单件面积 (平方米)，需要宽度和长度
+pub fn area_m2(info: &SpecInfo) -> Option<f64> {
+    let width = to_m(info.width.as_ref()?)?;
+    let length = to_m(info.length.as_ref()?)?;
+    Some(width * length)
+}
+
+/// 厚度分组标签，用于按厚度汇总 (例如 "0.05mm")
+pub fn gauge_label(info: &SpecInfo) -> Option<String> {
+    let mm = to_mm(info.thickness.as_ref()?)?;
+    Some(format!("{}mm", (mm * 10000.0).round() / 10000.0))
+}
+
+fn normalize_unit(unit: &str) -> String {
+    match unit {
+        "毫米" => "mm",
+        "厘米" => "cm",
+        "米" => "m",
+        "微米" | "μm" => "um",
+        "公斤" | "千克" => "kg",
+        "克" => "g",
+        other => other,
+    }
+    .to_string()
+}
+
+/// 提取重量后面的包装单位，例如 "25kg/包" 中的 "包"
+fn extract_pack_unit(spec: &str, pos: usize) -> String {
+    spec[pos..]
+        .trim_start()
+        .strip_prefix('/')
+        .and_then(|rest| rest.trim().chars().next())
+        .filter(|c| !c.is_ascii_digit())
+        .map(|c| c.to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value: f64, unit: &str) -> Option<SpecValue> {
+        Some(SpecValue {
+            value,
+            unit: unit.to_string(),
+        })
+    }
+
+    #[test]
+    fn parses_width_and_length() {
+        let info = parse_spec("160*1000米").unwrap();
+        assert_eq!(info.width, value(160.0, "mm"));
+        assert_eq!(info.length, value(1000.0, "m"));
+        assert_eq!(info.thickness, None);
+        assert!((area_m2(&info).unwrap() - 160.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parses_thickness() {
+        let info = parse_spec("0.05mm×500mm").unwrap();
+        assert_eq!(info.thickness, value(0.05, "mm"));
+        assert_eq!(info.width, value(500.0, "mm"));
+
+        let info = parse_spec("3丝*600*500m").unwrap();
+        assert_eq!(info.thickness, value(3.0, "丝"));
+        assert_eq!(info.width, value(600.0, "mm"));
+        assert_eq!(info.length, value(500.0, "m"));
+    }
+
+    #[test]
+    fn parses_pack_weight() {
+        let info = parse_spec("25kg/包").unwrap();
+        assert_eq!(info.pack_weight, value(25.0, "kg"));
+        assert_eq!(info.pack_unit, "包");
+        assert_eq!(to_kg(info.pack_weight.as_ref().unwrap()), Some(25.0));
+    }
+
+    #[test]
+    fn ignores_specs_without_dimensions() {
+        assert_eq!(parse_spec(""), None);
+        assert_eq!(parse_spec("A4"), None);
+        assert_eq!(parse_spec("红色"), None);
+    }
+
+    #[test]
+    fn detects_dimension_strings() {
+        assert!(looks_like_dimensions("160*1000米"));
+        assert!(looks_like_dimensions("600 x 500"));
+        assert!(!looks_like_dimensions("100米"));
+    }
+}
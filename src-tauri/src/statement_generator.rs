@@ -1,7 +1,23 @@
-use crate::models::{AppConfig, DeliveryItem};
-use anyhow::Result;
+use crate::error::{AppError, AppResult, IoAction};
+use crate::models::{AppConfig, DeliveryItem, OverwritePolicy, StatementAction};
 use rust_xlsxwriter::*;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 对账单中的一段明细 (合并对账单中每个子公司一段)
+struct Section<'a> {
+    title: Option<String>,
+    items: Vec<&'a DeliveryItem>,
+}
+
+/// 对账单文件名；集团合并对账单加 `_合并` 后缀，避免与同名子公司的对账单重名
+pub fn statement_file_name(customer: &str, period: &str, consolidated: bool) -> String {
+    if consolidated {
+        format!("statement_{}_{}_合并.xlsx", customer, period)
+    } else {
+        format!("statement_{}_{}.xlsx", customer, period)
+    }
+}
 
 /// 生成对账单
 pub fn generate_statement(
@@ -10,15 +26,121 @@ pub fn generate_statement(
     year_month: &str,
     output_file: &Path,
     config: &AppConfig,
-) -> Result<()> {
+) -> AppResult<()> {
+    let sections = vec![Section {
+        title: None,
+        items: items.iter().collect(),
+    }];
+    write_statement(&sections, customer_name, year_month, output_file, config)
+        .map_err(|e| e.with_file(output_file))
+}
+
+/// 生成集团合并对账单: 按子公司分段列出明细和小计，最后给出总计
+pub fn generate_consolidated_statement(
+    items: &[DeliveryItem],
+    parent_name: &str,
+    year_month: &str,
+    output_file: &Path,
+    config: &AppConfig,
+) -> AppResult<()> {
+    let mut by_child: BTreeMap<&str, Vec<&DeliveryItem>> = BTreeMap::new();
+    for item in items {
+        by_child.entry(item.customer.as_str()).or_default().push(item);
+    }
+
+    let sections: Vec<Section> = by_child
+        .into_iter()
+        .map(|(child, items)| Section {
+            title: Some(child.to_string()),
+            items,
+        })
+        .collect();
+    write_statement(&sections, parent_name, year_month, output_file, config)
+        .map_err(|e| e.with_file(output_file))
+}
+
+/// 对账单写入位置 (按覆盖策略决定)
+pub enum OutputPlan {
+    /// 写入文件，action 为新建、覆盖或另存新版本
+    Write {
+        file: PathBuf,
+        action: StatementAction,
+        reason: String,
+    },
+    /// 不生成
+    Skip { file: PathBuf, reason: String },
+}
+
+/// 按覆盖策略决定对账单写到哪里
+/// `recorded_hash` 为上次生成时的源数据指纹，`source_hash` 为本次的源数据指纹
+pub fn plan_output(
+    file: &Path,
+    policy: OverwritePolicy,
+    recorded_hash: Option<&str>,
+    source_hash: &str,
+) -> OutputPlan {
+    let write = |file: PathBuf, action, reason: &str| OutputPlan::Write {
+        file,
+        action,
+        reason: reason.to_string(),
+    };
+    let skip = |reason: &str| OutputPlan::Skip {
+        file: file.to_path_buf(),
+        reason: reason.to_string(),
+    };
+
+    if !file.exists() {
+        return write(file.to_path_buf(), StatementAction::Created, "");
+    }
+    match policy {
+        OverwritePolicy::Skip => skip("对账单已存在"),
+        OverwritePolicy::Overwrite => write(file.to_path_buf(), StatementAction::Replaced, "按设置覆盖"),
+        OverwritePolicy::OverwriteIfChanged => match recorded_hash {
+            Some(hash) if hash == source_hash => skip("源数据未变化"),
+            Some(_) => write(file.to_path_buf(), StatementAction::Replaced, "源数据已变化"),
+            None => skip("没有生成记录，无法判断源数据是否变化"),
+        },
+        OverwritePolicy::Versioned => match recorded_hash {
+            Some(hash) if hash == source_hash => skip("源数据未变化"),
+            _ => write(next_version(file), StatementAction::Versioned, "对账单已存在"),
+        },
+    }
+}
+
+/// 下一个未使用的版本文件名: statement_x.xlsx -> statement_x_v2.xlsx, statement_x_v3.xlsx ...
+fn next_version(file: &Path) -> PathBuf {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = file.extension().unwrap_or_default().to_string_lossy().to_string();
+    (2..)
+        .map(|version| file.with_file_name(format!("{}_v{}.{}", stem, version, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| file.to_path_buf())
+}
+
+fn write_statement(
+    sections: &[Section],
+    customer_name: &str,
+    year_month: &str,
+    output_file: &Path,
+    config: &AppConfig,
+) -> AppResult<()> {
+    let items: Vec<&DeliveryItem> = sections.iter().flat_map(|s| s.items.iter().copied()).collect();
     let mut workbook = Workbook::new();
     let worksheet = workbook.add_worksheet();
 
     // 检查是否包含订单号
     let has_order_no = items.iter().any(|i| !i.order_no.is_empty());
-    // 总列数索引 (例如：日期、送货单号、[订单号]、品名规格、单位、数量、单价、金额、备注)
-    // 有订单号共9列 (0-8)，无订单号共8列 (0-7)
-    let total_cols = if has_order_no { 8 } else { 7 };
+    // 检查是否有按重量/面积计价的条目
+    let has_billing = items.iter().copied().any(has_billing_quantity);
+    // 总列数索引 (例如：日期、送货单号、[订单号]、品名规格、单位、数量、[计价数量、计价单位]、单价、金额、备注)
+    // 基础共8列 (0-7)，订单号加1列，计价数量加2列
+    let mut total_cols = 7;
+    if has_order_no {
+        total_cols += 1;
+    }
+    if has_billing {
+        total_cols += 2;
+    }
 
     // 设置列宽
     worksheet.set_column_width(0, 12)?; // 日期
@@ -37,9 +159,17 @@ pub fn generate_statement(
     
     worksheet.set_column_width(current_col, 8)?;  // 单位
     current_col += 1;
-    let qty_col_idx = current_col;
+    let mut qty_col_idx = current_col;
     worksheet.set_column_width(current_col, 10)?; // 数量
     current_col += 1;
+    if has_billing {
+        // 金额按计价数量计算
+        qty_col_idx = current_col;
+        worksheet.set_column_width(current_col, 10)?; // 计价数量
+        current_col += 1;
+        worksheet.set_column_width(current_col, 8)?; // 计价单位
+        current_col += 1;
+    }
     let price_col_idx = current_col;
     worksheet.set_column_width(current_col, 10)?; // 单价
     current_col += 1;
@@ -119,83 +249,164 @@ pub fn generate_statement(
     if has_order_no {
         headers.push("订单号");
     }
-    headers.extend(["品名规格", "单位", "数量", "单价", "金额", "备注"]);
+    headers.extend(["品名规格", "单位", "数量"]);
+    if has_billing {
+        headers.extend(["计价数量", "计价单位"]);
+    }
+    headers.extend(["单价", "金额", "备注"]);
     
     for (col, header) in headers.iter().enumerate() {
         worksheet.write_with_format(4, col as u16, *header, &header_format)?;
     }
 
-    // 数据行
-    let mut sorted_items: Vec<&DeliveryItem> = items.iter().collect();
-    sorted_items.sort_by(|a, b| a.date.cmp(&b.date));
+    let section_format = Format::new()
+        .set_font_size(11)
+        .set_bold()
+        .set_align(FormatAlign::VerticalCenter)
+        .set_border(FormatBorder::Thin);
 
-    let start_data_row = 6; // Excel 1-based index for row 6 (index 5)
-    let mut last_data_row = start_data_row;
+    let subtotal_format = Format::new()
+        .set_font_size(10)
+        .set_bold()
+        .set_align(FormatAlign::Center)
+        .set_align(FormatAlign::VerticalCenter)
+        .set_border(FormatBorder::Thin)
+        .set_num_format("¥#,##0.00");
 
+    let amount_col_name = utility::column_number_to_name(amount_col_idx);
+
+    // 数据行
+    let mut row: u32 = 5;
     let mut total_amount = 0.0;
-    for (idx, item) in sorted_items.iter().enumerate() {
-        let row = (idx + 5) as u32;
-        let excel_row = row + 1;
-        last_data_row = excel_row;
-        let mut col = 0;
-
-        // 日期
-        worksheet.write_with_format(row, col, &format_date(&item.date), &cell_format)?;
-        col += 1;
-
-        // 送货单号
-        worksheet.write_with_format(row, col, &item.delivery_order_no, &cell_format)?;
-        col += 1;
-
-        // 订单号 (可选)
-        if has_order_no {
-            worksheet.write_with_format(row, col, &item.order_no, &cell_format)?;
-            col += 1;
+    let mut all_sorted: Vec<&DeliveryItem> = Vec::new();
+    // 合计引用: 不分段时为明细区域求和，分段时为各小计之和
+    let mut subtotal_cells = Vec::new();
+    let mut first_data_row = row + 1;
+
+    for section in sections {
+        let mut sorted_items = section.items.clone();
+        sorted_items.sort_by(|a, b| a.date.cmp(&b.date));
+
+        // 分段标题 (子公司名称)
+        if let Some(title) = &section.title {
+            worksheet.merge_range(row, 0, row, total_cols as u16, title, &section_format)?;
+            row += 1;
         }
 
-        // 品名规格
-        let product_spec = format!("{} {}", item.product_name, item.spec);
-        worksheet.write_with_format(row, col, &product_spec, &wrap_format)?;
-        col += 1;
+        let start_data_row = row + 1; // Excel 1-based
+        first_data_row = first_data_row.min(start_data_row);
 
-        // 单位
-        worksheet.write_with_format(row, col, &item.unit, &cell_format)?;
-        col += 1;
+        for item in &sorted_items {
+            let excel_row = row + 1;
+            let mut col = 0;
 
-        // 数量
-        worksheet.write_with_format(row, col, item.quantity, &cell_format)?;
-        col += 1;
+            // 日期
+            worksheet.write_with_format(row, col, format_date(&item.date), &cell_format)?;
+            col += 1;
+
+            // 送货单号
+            worksheet.write_with_format(row, col, &item.delivery_order_no, &cell_format)?;
+            col += 1;
 
-        // 单价
-        worksheet.write_with_format(row, col, item.unit_price, &cell_format)?;
-        col += 1;
+            // 订单号 (可选)
+            if has_order_no {
+                worksheet.write_with_format(row, col, &item.order_no, &cell_format)?;
+                col += 1;
+            }
 
-        // 金额 (公式: 数量 * 单价)
-        let qty_cell = format!("{}{}", utility::column_number_to_name(qty_col_idx as u16), excel_row);
-        let price_cell = format!("{}{}", utility::column_number_to_name(price_col_idx as u16), excel_row);
-        let amount_formula = format!("={}*{}", qty_cell, price_cell);
-        worksheet.write_formula_with_format(row, col, amount_formula.as_str(), &amount_cell_format)?;
-        col += 1;
+            // 品名规格
+            let product_spec = format!("{} {}", item.product_name, item.spec);
+            worksheet.write_with_format(row, col, &product_spec, &wrap_format)?;
+            col += 1;
 
-        // 备注
-        worksheet.write_with_format(row, col, "", &cell_format)?;
+            // 单位
+            worksheet.write_with_format(row, col, &item.unit, &cell_format)?;
+            col += 1;
 
-        total_amount += item.amount;
+            // 数量
+            worksheet.write_with_format(row, col, item.quantity, &cell_format)?;
+            col += 1;
+
+            // 计价数量、计价单位 (可选)
+            if has_billing {
+                worksheet.write_with_format(row, col, billing_quantity(item), &cell_format)?;
+                col += 1;
+                worksheet.write_with_format(row, col, billing_unit(item), &cell_format)?;
+                col += 1;
+            }
+
+            // 单价
+            worksheet.write_with_format(row, col, item.unit_price, &cell_format)?;
+            col += 1;
+
+            // 金额 (公式: 计价数量 * 单价)
+            let qty_cell = format!("{}{}", utility::column_number_to_name(qty_col_idx), excel_row);
+            let price_cell = format!("{}{}", utility::column_number_to_name(price_col_idx), excel_row);
+            let amount_formula = format!("={}*{}", qty_cell, price_cell);
+            worksheet.write_formula_with_format(row, col, amount_formula.as_str(), &amount_cell_format)?;
+            col += 1;
+
+            // 备注 (标记赠品、价目表补录的单价和已修正的数据)
+            let mut remarks = Vec::new();
+            if item.is_free {
+                remarks.push("赠品");
+            }
+            if item.price_filled {
+                remarks.push("按价目表补价");
+            }
+            if !item.corrections.is_empty() {
+                remarks.push("已修正");
+            }
+            worksheet.write_with_format(row, col, remarks.join("；"), &cell_format)?;
+
+            total_amount += item.amount;
+            row += 1;
+        }
+
+        // 分段小计
+        if let Some(title) = &section.title {
+            let section_amount: f64 = sorted_items.iter().map(|i| i.amount).sum();
+            worksheet.merge_range(
+                row,
+                0,
+                row,
+                amount_col_idx - 1,
+                &format!("{} 小计", title),
+                &header_format,
+            )?;
+            let subtotal_formula = Formula::new(format!(
+                "=SUM({0}{1}:{0}{2})",
+                amount_col_name, start_data_row, row
+            ))
+            .set_result(format!("{:.2}", section_amount));
+            worksheet.write_formula_with_format(row, amount_col_idx, subtotal_formula, &subtotal_format)?;
+            worksheet.write_with_format(row, amount_col_idx + 1, "", &cell_format)?;
+            subtotal_cells.push(format!("{}{}", amount_col_name, row + 1));
+            row += 1;
+        }
+
+        all_sorted.extend(sorted_items);
     }
+    let sorted_items = all_sorted;
+    let last_data_row = row;
 
     // 合计行
-    let summary_row = (sorted_items.len() + 7) as u32;
-    let amount_col_name = utility::column_number_to_name(amount_col_idx as u16);
+    let summary_row = row + 2;
     
     // 预计算初始大写文字 (用于 Numbers 等不支持公式的环境)
     let initial_chinese = amount_to_chinese(total_amount);
     
     // 构造大写转换公式 (针对 Excel/WPS 环境)
-    let sum_ref = format!("SUM({}{}:{}{})", amount_col_name, start_data_row, amount_col_name, last_data_row);
-    let caps_formula = format!(
+    let sum_ref = if subtotal_cells.is_empty() {
+        format!("SUM({}{}:{}{})", amount_col_name, first_data_row, amount_col_name, last_data_row)
+    } else {
+        format!("({})", subtotal_cells.join("+"))
+    };
+    let caps_formula = Formula::new(format!(
         "=\"合计人民币大写：\" & IF({0}=0,\"零元整\",IF({0}<0,\"负\",\"\") & SUBSTITUTE(SUBSTITUTE(SUBSTITUTE(TEXT(INT(ABS({0})),\"[DBNum2]0元\") & TEXT(MOD(INT(ABS({0})*10),10),\"[DBNum2]0角\") & TEXT(MOD(INT(ABS({0})*100),10),\"[DBNum2]0分\"),\"零角零分\",\"整\"),\"零分\",\"整\"),\"零角\",\"零\"))",
         sum_ref
-    );
+    ))
+    .set_result(format!("合计人民币大写：{}", initial_chinese));
 
     // 中文大写合计
     worksheet.merge_range(
@@ -207,21 +418,16 @@ pub fn generate_statement(
         &Format::new().set_font_size(11),
     )?;
     
-    // 写入公式。注意：由于 Numbers 不支持此公式，它可能会显示错误或 0。
-    // 但在生成时，我们已经提供了初始金额。
+    // 写入公式，并带上预计算的大写文字作为缓存结果，不计算公式的软件直接显示该文字
     worksheet.write_formula_with_format(
         summary_row,
         0,
-        caps_formula.as_str(),
+        caps_formula,
         &Format::new().set_font_size(11)
     )?;
-    
-    // 如果是第一次打开，公式可能还没运行，有些软件会显示我们写入的“默认值”
-    // 这里我们强制写入初始文字作为占位（部分软件支持）
-    // worksheet.write_string(summary_row, 0, &format!("合计人民币大写：{}", initial_chinese), &Format::new().set_font_size(11))?;
 
     // 数字总计公式 (SUM)
-    let sum_formula = format!("=SUM({}{}:{}{})", amount_col_name, start_data_row, amount_col_name, last_data_row);
+    let sum_formula = format!("={}", sum_ref);
     
     let total_label_format = Format::new().set_font_size(11).set_align(FormatAlign::Right);
     worksheet.merge_range(summary_row, 4, summary_row, total_cols as u16, "", &total_label_format)?;
@@ -233,10 +439,91 @@ pub fn generate_statement(
         &Format::new().set_font_size(11).set_align(FormatAlign::Right).set_num_format("\"人民币小写：\"¥#,##0.00\"元\"")
     )?;
 
-    workbook.save(output_file)?;
+    // 修正记录 (审计用)
+    if sorted_items.iter().any(|i| !i.corrections.is_empty()) {
+        write_correction_sheet(&mut workbook, &sorted_items, &header_format, &cell_format)?;
+    }
+
+    save_atomically(&mut workbook, output_file)
+}
+
+/// 先写入临时文件再改名，避免中断时留下写了一半的对账单
+fn save_atomically(workbook: &mut Workbook, output_file: &Path) -> AppResult<()> {
+    let mut temp_name = output_file.as_os_str().to_owned();
+    temp_name.push(".partial");
+    let temp_file = PathBuf::from(temp_name);
+
+    let result = workbook.save(&temp_file).map_err(AppError::from).and_then(|_| {
+        std::fs::rename(&temp_file, output_file).map_err(|e| AppError::io(IoAction::WriteFile, output_file, e))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_file);
+    }
+    result
+}
+
+/// 写入修正记录工作表，列出对账单中被修正过的字段及原始值
+fn write_correction_sheet(
+    workbook: &mut Workbook,
+    items: &[&DeliveryItem],
+    header_format: &Format,
+    cell_format: &Format,
+) -> AppResult<()> {
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name("修正记录")?;
+
+    let headers = ["送货日期", "送货单号", "品名规格", "字段", "原值", "修正值", "源文件", "行号"];
+    let widths = [12, 15, 25, 8, 15, 15, 40, 8];
+    for (col, (header, width)) in headers.iter().zip(widths).enumerate() {
+        worksheet.set_column_width(col as u16, width)?;
+        worksheet.write_with_format(0, col as u16, *header, header_format)?;
+    }
+
+    let mut row = 1;
+    for item in items {
+        let file_name = Path::new(&item.source_file)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        for change in &item.corrections {
+            worksheet.write_with_format(row, 0, format_date(&item.date), cell_format)?;
+            worksheet.write_with_format(row, 1, &item.delivery_order_no, cell_format)?;
+            worksheet.write_with_format(row, 2, format!("{} {}", item.product_name, item.spec), cell_format)?;
+            worksheet.write_with_format(row, 3, &change.field, cell_format)?;
+            worksheet.write_with_format(row, 4, &change.original, cell_format)?;
+            worksheet.write_with_format(row, 5, &change.corrected, cell_format)?;
+            worksheet.write_with_format(row, 6, &file_name, cell_format)?;
+            worksheet.write_with_format(row, 7, item.source_row as u32, cell_format)?;
+            row += 1;
+        }
+    }
+
     Ok(())
 }
 
+/// 是否按重量/面积等换算后的数量计价
+fn has_billing_quantity(item: &DeliveryItem) -> bool {
+    billing_unit(item) != item.unit || billing_quantity(item) != item.quantity
+}
+
+/// 计价数量 (旧数据没有计价数量时沿用送货数量)
+fn billing_quantity(item: &DeliveryItem) -> f64 {
+    if item.billing_unit.is_empty() {
+        item.quantity
+    } else {
+        item.billing_quantity
+    }
+}
+
+fn billing_unit(item: &DeliveryItem) -> &str {
+    if item.billing_unit.is_empty() {
+        &item.unit
+    } else {
+        &item.billing_unit
+    }
+}
+
 /// 格式化日期
 fn format_date(date_str: &str) -> String {
     // 尝试解析日期并格式化
@@ -302,7 +589,7 @@ fn amount_to_chinese(amount: f64) -> String {
         result = "零".to_string();
     }
 
-    result.push_str("元");
+    result.push('元');
 
     // 处理角分
     let jiao = decimal_part
@@ -317,17 +604,101 @@ fn amount_to_chinese(amount: f64) -> String {
         .unwrap_or(0) as usize;
 
     if jiao == 0 && fen == 0 {
-        result.push_str("整");
+        result.push('整');
     } else {
         if jiao != 0 {
             result.push_str(chinese_numbers[jiao]);
-            result.push_str("角");
+            result.push('角');
         }
         if fen != 0 {
             result.push_str(chinese_numbers[fen]);
-            result.push_str("分");
+            result.push('分');
         }
     }
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// 测试用的临时目录 (每个测试独立)
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("statement_generator_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn action_of(plan: &OutputPlan) -> Option<StatementAction> {
+        match plan {
+            OutputPlan::Write { action, .. } => Some(*action),
+            OutputPlan::Skip { .. } => None,
+        }
+    }
+
+    #[test]
+    fn creates_missing_file_under_every_policy() {
+        let file = temp_dir("create").join("statement_客户甲_2024年1月.xlsx");
+        for policy in [
+            OverwritePolicy::Skip,
+            OverwritePolicy::Overwrite,
+            OverwritePolicy::OverwriteIfChanged,
+            OverwritePolicy::Versioned,
+        ] {
+            let plan = plan_output(&file, policy, None, "hash");
+            assert_eq!(action_of(&plan), Some(StatementAction::Created));
+        }
+    }
+
+    #[test]
+    fn existing_file_follows_policy() {
+        let file = temp_dir("existing").join("statement_客户甲_2024年1月.xlsx");
+        fs::write(&file, b"").unwrap();
+
+        assert_eq!(action_of(&plan_output(&file, OverwritePolicy::Skip, None, "new")), None);
+        assert_eq!(
+            action_of(&plan_output(&file, OverwritePolicy::Overwrite, Some("new"), "new")),
+            Some(StatementAction::Replaced)
+        );
+
+        // 按源数据是否变化
+        assert_eq!(action_of(&plan_output(&file, OverwritePolicy::OverwriteIfChanged, Some("new"), "new")), None);
+        assert_eq!(
+            action_of(&plan_output(&file, OverwritePolicy::OverwriteIfChanged, Some("old"), "new")),
+            Some(StatementAction::Replaced)
+        );
+        assert_eq!(action_of(&plan_output(&file, OverwritePolicy::OverwriteIfChanged, None, "new")), None);
+
+        // 另存新版本: 源数据未变化时不再另存
+        assert_eq!(action_of(&plan_output(&file, OverwritePolicy::Versioned, Some("new"), "new")), None);
+        match plan_output(&file, OverwritePolicy::Versioned, Some("old"), "new") {
+            OutputPlan::Write { file: versioned, action, .. } => {
+                assert_eq!(action, StatementAction::Versioned);
+                assert_eq!(versioned, file.with_file_name("statement_客户甲_2024年1月_v2.xlsx"));
+            }
+            OutputPlan::Skip { .. } => panic!("源数据变化时应另存新版本"),
+        }
+    }
+
+    #[test]
+    fn next_version_skips_existing_versions() {
+        let dir = temp_dir("version");
+        let file = dir.join("statement_客户甲_2024年1月.xlsx");
+        assert_eq!(next_version(&file), dir.join("statement_客户甲_2024年1月_v2.xlsx"));
+
+        fs::write(dir.join("statement_客户甲_2024年1月_v2.xlsx"), b"").unwrap();
+        fs::write(dir.join("statement_客户甲_2024年1月_v3.xlsx"), b"").unwrap();
+        assert_eq!(next_version(&file), dir.join("statement_客户甲_2024年1月_v4.xlsx"));
+    }
+
+    #[test]
+    fn consolidated_file_name_differs_from_child() {
+        assert_ne!(
+            statement_file_name("客户甲", "2024年1月", true),
+            statement_file_name("客户甲", "2024年1月", false)
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use crate::data_processor::parse_date;
+use crate::models::{DeliveryItem, FileValidationError, PricingRule, RuleConfig, Severity, ValidationConfig};
+use crate::pricing::expected_billing_quantity;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// 校验规则定义
+#[derive(Serialize)]
+pub struct RuleDef {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub default_severity: Severity,
+}
+
+/// 规则注册表 (所有校验结果都带有其中的规则 id)
+pub const RULES: &[RuleDef] = &[
+    RuleDef { id: "parse_failure", name: "文件解析失败", default_severity: Severity::Error },
+    RuleDef { id: "empty_file", name: "文件无有效数据", default_severity: Severity::Warning },
+    RuleDef { id: "date_parse", name: "日期无法识别", default_severity: Severity::Error },
+    RuleDef { id: "filename_date", name: "文件名日期与内容不一致", default_severity: Severity::Warning },
+    RuleDef { id: "duplicate_order_no", name: "送货单号重复", default_severity: Severity::Warning },
+    RuleDef { id: "required_fields", name: "必填字段缺失", default_severity: Severity::Warning },
+    RuleDef { id: "amount_reconciliation", name: "金额与数量×单价不符", default_severity: Severity::Warning },
+    RuleDef { id: "price_range", name: "单价超出范围", default_severity: Severity::Warning },
+    RuleDef { id: "unit_whitelist", name: "单位不在允许列表中", default_severity: Severity::Warning },
+    RuleDef { id: "number_format", name: "数字格式异常", default_severity: Severity::Warning },
+    RuleDef { id: "quantity_unreadable", name: "数量无法识别", default_severity: Severity::Error },
+    RuleDef { id: "date_window", name: "日期超出允许范围", default_severity: Severity::Info },
+    RuleDef { id: "contract_price", name: "单价与合同价不符", default_severity: Severity::Warning },
+    RuleDef { id: "zero_price", name: "零单价或缺少单价", default_severity: Severity::Warning },
+    RuleDef { id: "pricing_conversion", name: "计价数量无法换算", default_severity: Severity::Warning },
+    RuleDef { id: "stale_correction", name: "文件变更后修正未应用", default_severity: Severity::Warning },
+    RuleDef { id: "invalid_pattern", name: "作废清单或单号格式的正则无效", default_severity: Severity::Warning },
+];
+
+/// 必填字段参数
+#[derive(Deserialize)]
+#[serde(default)]
+struct RequiredFieldsParams {
+    fields: Vec<String>,
+}
+
+impl Default for RequiredFieldsParams {
+    fn default() -> Self {
+        Self {
+            fields: ["customer", "date", "delivery_order_no", "product_name", "unit"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// 金额核对参数
+#[derive(Deserialize)]
+#[serde(default)]
+struct AmountParams {
+    tolerance: f64,
+}
+
+impl Default for AmountParams {
+    fn default() -> Self {
+        Self { tolerance: 0.01 }
+    }
+}
+
+/// 单价范围参数 (max 为 0 表示不限)
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PriceRangeParams {
+    min: f64,
+    max: f64,
+}
+
+/// 单位白名单参数 (为空时不检查)
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct UnitParams {
+    units: Vec<String>,
+}
+
+/// 数字格式参数
+#[derive(Deserialize)]
+#[serde(default)]
+struct NumberFormatParams {
+    max_decimals: u32,
+}
+
+impl Default for NumberFormatParams {
+    fn default() -> Self {
+        Self { max_decimals: 4 }
+    }
+}
+
+/// 日期范围参数 (相对今天的天数，0 表示不限)
+#[derive(Deserialize)]
+#[serde(default)]
+struct DateWindowParams {
+    max_past_days: i64,
+    max_future_days: i64,
+}
+
+impl Default for DateWindowParams {
+    fn default() -> Self {
+        Self {
+            max_past_days: 0,
+            max_future_days: 30,
+        }
+    }
+}
+
+/// 创建一条校验结果，严重级别先取规则默认值
+pub fn finding(rule_id: &str, file: &str, error: String) -> FileValidationError {
+    let severity = RULES
+        .iter()
+        .find(|r| r.id == rule_id)
+        .map(|r| r.default_severity.clone())
+        .unwrap_or(Severity::Warning);
+
+    FileValidationError {
+        file: file.to_string(),
+        error,
+        rule_id: rule_id.to_string(),
+        severity,
+        row: None,
+    }
+}
+
+/// 创建一条针对某一行的校验结果
+pub fn item_finding(rule_id: &str, item: &DeliveryItem, error: String) -> FileValidationError {
+    FileValidationError {
+        row: Some(item.source_row),
+        ..finding(rule_id, &item.source_file, error)
+    }
+}
+
+fn rule_config<'a>(config: &'a ValidationConfig, rule_id: &str) -> Option<&'a RuleConfig> {
+    config.rules.iter().find(|r| r.id == rule_id)
+}
+
+fn rule_enabled(config: &ValidationConfig, rule_id: &str) -> bool {
+    rule_config(config, rule_id).is_none_or(|r| r.enabled)
+}
+
+fn rule_params<T: DeserializeOwned + Default>(config: &ValidationConfig, rule_id: &str) -> T {
+    rule_config(config, rule_id)
+        .and_then(|r| serde_json::from_value(r.params.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// 按配置调整严重级别，去掉已禁用或已屏蔽的结果
+pub fn apply_rule_settings(
+    findings: Vec<FileValidationError>,
+    config: &ValidationConfig,
+) -> Vec<FileValidationError> {
+    findings
+        .into_iter()
+        .filter(|f| rule_enabled(config, &f.rule_id))
+        .filter(|f| {
+            !config.suppressions.iter().any(|s| {
+                s.rule_id == f.rule_id
+                    && (s.file.is_empty() || f.file.contains(&s.file))
+                    && (s.message.is_empty() || f.error.contains(&s.message))
+            })
+        })
+        .map(|mut f| {
+            if let Some(severity) = rule_config(config, &f.rule_id).and_then(|r| r.severity.clone()) {
+                f.severity = severity;
+            }
+            f
+        })
+        .collect()
+}
+
+/// 按严重级别拆分为 (错误, 警告, 提示)
+pub fn split_by_severity(
+    findings: Vec<FileValidationError>,
+) -> (
+    Vec<FileValidationError>,
+    Vec<FileValidationError>,
+    Vec<FileValidationError>,
+) {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let mut infos = Vec::new();
+
+    for f in findings {
+        match f.severity {
+            Severity::Error => errors.push(f),
+            Severity::Warning => warnings.push(f),
+            Severity::Info => infos.push(f),
+        }
+    }
+
+    (errors, warnings, infos)
+}
+
+/// 对单个文件的条目执行可配置的字段规则
+/// 金额核对按计价规则换算后的计价数量计算 (此时计价规则尚未应用到条目上)
+pub fn check_items(
+    items: &[DeliveryItem],
+    config: &ValidationConfig,
+    pricing_rules: &[PricingRule],
+) -> Vec<FileValidationError> {
+    let mut findings = Vec::new();
+
+    let required: RequiredFieldsParams = rule_params(config, "required_fields");
+    let amount: AmountParams = rule_params(config, "amount_reconciliation");
+    let price_range: PriceRangeParams = rule_params(config, "price_range");
+    let units: UnitParams = rule_params(config, "unit_whitelist");
+    let number_format: NumberFormatParams = rule_params(config, "number_format");
+    let date_window: DateWindowParams = rule_params(config, "date_window");
+    let today = chrono::Local::now().date_naive();
+
+    for item in items {
+        let line = format!("送货单号 '{}' 的 '{} {}'", item.delivery_order_no, item.product_name, item.spec);
+
+        // 必填字段
+        for field in &required.fields {
+            let (label, value) = match field.as_str() {
+                "customer" => ("客户", &item.customer),
+                "date" => ("日期", &item.date),
+                "delivery_order_no" => ("送货单号", &item.delivery_order_no),
+                "order_no" => ("订单号", &item.order_no),
+                "product_name" => ("货名", &item.product_name),
+                "spec" => ("规格", &item.spec),
+                "unit" => ("单位", &item.unit),
+                _ => continue,
+            };
+            if value.trim().is_empty() {
+                findings.push(item_finding("required_fields", item, format!("缺少{}: {}", label, line)));
+            }
+        }
+
+        // 金额核对 (单价和金额都有时才比较)
+        if item.unit_price != 0.0 && item.amount != 0.0 {
+            let expected = expected_billing_quantity(item, pricing_rules) * item.unit_price;
+            if (expected - item.amount).abs() > amount.tolerance {
+                findings.push(item_finding(
+                    "amount_reconciliation",
+                    item,
+                    format!("金额不符: {} 计价数量×单价为 {:.2}，金额为 {:.2}", line, expected, item.amount),
+                ));
+            }
+        }
+
+        // 单价范围
+        if item.unit_price != 0.0
+            && (item.unit_price < price_range.min || (price_range.max > 0.0 && item.unit_price > price_range.max))
+        {
+            findings.push(item_finding(
+                "price_range",
+                item,
+                format!("单价超出范围: {} 单价 {}", line, item.unit_price),
+            ));
+        }
+
+        // 单位白名单
+        if !units.units.is_empty() && !units.units.contains(&item.unit) {
+            findings.push(item_finding(
+                "unit_whitelist",
+                item,
+                format!("单位不在允许列表中: {} 单位 '{}'", line, item.unit),
+            ));
+        }
+
+        // 数量无法识别 (例如数量列误填了规格)，默认拒绝该行
+        if !item.unreadable_quantity.is_empty() {
+            findings.push(item_finding(
+                "quantity_unreadable",
+                item,
+                format!("数量无法识别: {} 数量单元格为 '{}'", line, item.unreadable_quantity),
+            ));
+        }
+
+        // 数字格式 (负数或小数位过多)
+        for (label, value) in [("数量", item.quantity), ("单价", item.unit_price), ("金额", item.amount)] {
+            if value < 0.0 {
+                findings.push(item_finding("number_format", item, format!("{}为负数: {} {}", label, line, value)));
+            } else if decimal_places(value) > number_format.max_decimals {
+                findings.push(item_finding(
+                    "number_format",
+                    item,
+                    format!("{}小数位过多: {} {}", label, line, value),
+                ));
+            }
+        }
+
+        // 日期范围
+        if let Ok(date) = parse_date(&item.date) {
+            let days = (today - date).num_days();
+            let too_old = date_window.max_past_days > 0 && days > date_window.max_past_days;
+            let too_new = date_window.max_future_days > 0 && -days > date_window.max_future_days;
+            if too_old || too_new {
+                findings.push(item_finding(
+                    "date_window",
+                    item,
+                    format!("日期超出允许范围: {} 日期 {}", line, item.date),
+                ));
+            }
+        }
+    }
+
+    findings
+}
+
+/// 小数位数 (最多数到 10 位，用于过滤浮点误差)
+fn decimal_places(value: f64) -> u32 {
+    let text = format!("{:.10}", value.abs());
+    text.trim_end_matches('0')
+        .split('.')
+        .nth(1)
+        .map(|d| d.len() as u32)
+        .unwrap_or(0)
+}
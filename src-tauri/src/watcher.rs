@@ -0,0 +1,172 @@
+use crate::corrections::load_corrections;
+use crate::data_processor::{
+    customer_type_of, is_workbook_file, raw_data_root, validate_delivery_data,
+};
+use crate::duplicates::resolve_duplicates;
+use crate::error::{AppError, AppResult};
+use crate::exclusions::VoidList;
+use crate::jobs::JobRegistry;
+use crate::ledger;
+use crate::models::{AppConfig, DeliveryItem, IngestResult};
+use crate::progress::ProgressReporter;
+use crate::scan_cache::ScanCache;
+use crate::sequence_check::{check_order_sequences, compile_order_formats};
+use crate::sessions::SessionStore;
+use crate::validation::split_by_severity;
+use crate::workers::worker_count;
+use anyhow::Result;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+/// 文件变化后等待的时间，合并 Excel 保存时的多次写入
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// 原始数据目录监视器 (Tauri 托管状态)
+#[derive(Default)]
+pub struct WatcherState {
+    debouncer: Mutex<Option<Debouncer<RecommendedWatcher>>>,
+}
+
+impl WatcherState {
+    /// 开始监视原始数据目录 (已在监视时先停止旧的监视)
+    /// 只使用 `config` 中的原始数据目录；其他设置在每批变化时重新读取，保存配置后立即生效
+    pub fn start(&self, app: &tauri::AppHandle, config: AppConfig) -> AppResult<()> {
+        self.stop();
+
+        // 与扫描相同使用规范化路径，事件中的文件路径与台账中的记录一致
+        let root = raw_data_root(&config.raw_data_path)?;
+        let watched_path = config.raw_data_path.clone();
+        let watch_error = |e: notify_debouncer_mini::notify::Error| AppError::Watcher {
+            path: watched_path.clone(),
+            reason: e.to_string(),
+        };
+        let handler_app = app.clone();
+        let handler_root = root.clone();
+        let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+            let Ok(events) = result else {
+                return;
+            };
+            let paths: BTreeSet<PathBuf> = events
+                .into_iter()
+                .map(|e| e.path)
+                .filter(|p| is_workbook_file(p))
+                .collect();
+            if paths.is_empty() {
+                return;
+            }
+
+            let config = crate::load_config();
+            match ingest_files(&handler_app, &handler_root, &config, &paths) {
+                Ok(result) => {
+                    let _ = handler_app.emit("ingest", result);
+                }
+                Err(e) => {
+                    let _ = handler_app.emit("log", format!("自动导入失败: {}", e));
+                }
+            }
+        })
+        .map_err(watch_error)?;
+
+        debouncer
+            .watcher()
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(watch_error)?;
+
+        *self.debouncer.lock().unwrap() = Some(debouncer);
+        Ok(())
+    }
+
+    /// 停止监视
+    pub fn stop(&self) {
+        self.debouncer.lock().unwrap().take();
+    }
+}
+
+/// 只解析和校验发生变化的文件，写入台账
+/// 跨文件检查 (重复行、单号缺号) 针对台账中本目录的全部数据，并以台账数据生成新的扫描会话
+fn ingest_files(
+    app: &tauri::AppHandle,
+    root: &Path,
+    config: &AppConfig,
+    paths: &BTreeSet<PathBuf>,
+) -> Result<IngestResult> {
+    let (existing, removed): (Vec<&PathBuf>, Vec<&PathBuf>) = paths.iter().partition(|p| p.exists());
+    let removed: Vec<String> = removed.iter().map(|p| p.to_string_lossy().to_string()).collect();
+
+    // 作废的文件不导入
+    let void_list = VoidList::new(&config.void_rules);
+    let files: Vec<(PathBuf, String)> = existing
+        .into_iter()
+        .filter(|p| void_list.match_file(p).is_none())
+        .map(|p| (p.clone(), customer_type_of(root, p)))
+        .collect();
+
+    let mut conn = ledger::open_ledger()?;
+    let mut cache = ScanCache::load(&conn)?;
+    let progress = ProgressReporter::new(app, Arc::new(AtomicBool::new(false)));
+
+    let report = validate_delivery_data(
+        &files,
+        config,
+        &void_list,
+        &load_corrections(),
+        &mut cache,
+        worker_count(config.max_workers),
+        &progress,
+    );
+    let changes = cache.finish_partial(&removed);
+
+    // 与正在运行的扫描、生成任务依次读写台账 (解析期间不持有台账锁)
+    let registry = app.state::<JobRegistry>();
+    let ledger_lock = registry.lock_ledger();
+    cache.save(&mut conn)?;
+
+    let items = report.items;
+    let (errors, warnings, _) = split_by_severity(report.findings);
+
+    // 删除的文件在台账中保留历史数据 (标记为已归档)
+    let scanned: Vec<String> = files.iter().map(|(p, _)| p.to_string_lossy().to_string()).collect();
+    ledger::sync_scan(&mut conn, &scanned, &items)?;
+
+    // 缺号检查同时使用本批被拒绝和日期待指定的行，避免把这些单号误报为缺号
+    let ledger_items = ledger::load_items(&conn, root, config.include_archived)?;
+    drop(ledger_lock);
+    let (order_formats, _) = compile_order_formats(&config.order_no_books);
+    let sequence_items: Vec<DeliveryItem> = ledger_items
+        .iter()
+        .chain(report.rejected_rows.iter().map(|r| &r.item))
+        .chain(report.unassigned.iter())
+        .cloned()
+        .collect();
+    let sequence_issues = check_order_sequences(&sequence_items, &order_formats);
+    let (ledger_items, duplicates) =
+        resolve_duplicates(ledger_items, &config.duplicate_policy, &config.duplicate_resolutions);
+
+    let sessions = app.state::<SessionStore>();
+    let (session_id, statements) =
+        crate::open_session(&sessions, ledger_items, config, &warnings, &duplicates);
+
+    let mut changed = changes.added;
+    changed.extend(changes.changed);
+    changed.sort();
+
+    Ok(IngestResult {
+        changed,
+        removed: changes.removed,
+        imported_rows: items.len(),
+        errors,
+        warnings,
+        rejected_rows: report.rejected_rows,
+        unassigned: report.unassigned,
+        duplicates,
+        sequence_issues,
+        session_id,
+        statements,
+    })
+}
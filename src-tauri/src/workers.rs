@@ -0,0 +1,57 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// 分阶段进度报告 (可在工作线程中调用)
+pub trait Progress: Sync {
+    /// 开始新阶段，total 为该阶段的文件数 (未知时为 0)
+    fn start(&self, step: &str, total: usize);
+    /// 当前阶段完成一个文件，status 为该文件的处理结果
+    fn advance(&self, file: &str, status: &str);
+    /// 任务是否已被取消 (在文件之间检查)
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// 工作线程数: 配置为 0 时按 CPU 核数
+pub fn worker_count(configured: usize) -> usize {
+    if configured > 0 {
+        configured
+    } else {
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(4)
+    }
+}
+
+/// 用固定数量的工作线程并行处理，结果顺序与输入顺序一致
+pub fn run_parallel<T, R, F>(inputs: &[T], workers: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = workers.clamp(1, inputs.len().max(1));
+    if workers == 1 {
+        return inputs.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<(usize, R)>> = Mutex::new(Vec::with_capacity(inputs.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(idx) else {
+                    break;
+                };
+                let result = f(input);
+                results.lock().unwrap().push((idx, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, r)| r).collect()
+}
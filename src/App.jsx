@@ -28,6 +28,20 @@ import {
   RefreshCw
 } from "lucide-react";
 
+// 明细表每页条数
+const ITEM_PAGE_SIZE = 200;
+
+const ITEM_STATUS_LABELS = {
+  ok: "正常",
+  corrected: "已修正",
+  warning: "有警告",
+  duplicate: "重复",
+};
+
+// 后端错误为 { code, message_key, params, message, file, sheet, cell }，其他错误 (例如插件) 可能是字符串
+const errorMessage = (error) =>
+  typeof error === "string" ? error : (error?.message || JSON.stringify(error));
+
 function App() {
   const [config, setConfig] = useState({
     company_name: "",
@@ -48,12 +62,25 @@ function App() {
 
   // Dashboard State
   const [currentView, setCurrentView] = useState("preview"); // 'preview' | 'analysis'
-  const [dashboardData, setDashboardData] = useState({ map: {}, customers: [], allItems: [] });
+  const [dashboardData, setDashboardData] = useState({ sessionId: null, map: {}, customers: [], customerTypeMap: {} });
+  const [currentItems, setCurrentItems] = useState([]); // 当前客户当前月份的明细 (按需从后端分页查询)
+  const [itemTotals, setItemTotals] = useState({ total: 0, quantity: 0, amount: 0 }); // 当前筛选条件下的合计
+  const [itemFilter, setItemFilter] = useState({ product: "", delivery_order_no: "", status: "" });
+  const [itemSort, setItemSort] = useState({ sort_by: null, descending: false });
+  const [itemOffset, setItemOffset] = useState(0);
+  const [analysisItems, setAnalysisItems] = useState([]); // 分析对象的明细
   const [selectedCustomer, setSelectedCustomer] = useState(null);
   const [selectedMonth, setSelectedMonth] = useState(null);
   const [searchTerm, setSearchTerm] = useState("");
   const [filterType, setFilterType] = useState("all"); // 'all' | 'monthly' | 'cash' | etc.
   const [isLoadingData, setIsLoadingData] = useState(false);
+  const [currentJobId, setCurrentJobId] = useState(null); // 正在运行、可取消的任务
+  const [progress, setProgress] = useState(null); // 当前解析/生成进度 { step, current, total, message }
+  const [unassignedItems, setUnassignedItems] = useState([]); // 日期无法识别、待指定日期的条目
+  const [duplicateGroups, setDuplicateGroups] = useState([]); // 跨文件重复行 (并排对比)
+  const [rejectedRows, setRejectedRows] = useState([]); // 有错误被拒绝的行 (可修正，或按文件选择处理方式)
+  const [rowFixes, setRowFixes] = useState({}); // "file#row" -> 填写的修正值
+  const [assignDates, setAssignDates] = useState({}); // "file#row" -> 指定的日期
 
   // Analysis State
   const [analysisTarget, setAnalysisTarget] = useState("all"); // 'all' or customerName
@@ -71,8 +98,37 @@ function App() {
     const unlisten = listen("log", (event) => {
       addLog(event.payload, "info");
     });
+    const unlistenIngest = listen("ingest", (event) => {
+      const r = event.payload;
+      if (r.changed.length > 0) {
+        addLog(`自动导入: ${r.changed.length} 个文件有变化，导入 ${r.imported_rows} 行`, "success");
+      }
+      if (r.removed.length > 0) {
+        addLog(`检测到 ${r.removed.length} 个文件被删除 (台账保留历史数据)`, "info");
+      }
+      [...r.errors, ...r.warnings].forEach((w) => {
+        const fileName = w.file.split(/[/\\]/).pop();
+        addLog(`${fileName}: ${w.error}`, w.severity === "error" ? "error" : "warning");
+      });
+      r.sequence_issues.forEach((issue) => addLog(issue.message, "warning"));
+      // 按导入结果更新界面: 变化和删除的文件的被拒绝行、待指定日期的行替换为本次结果，会话和重复行使用台账的最新数据
+      const touched = new Set([...r.changed, ...r.removed]);
+      setRejectedRows((prev) => [...prev.filter((x) => !touched.has(x.file)), ...r.rejected_rows]);
+      setUnassignedItems((prev) => [...prev.filter((x) => !touched.has(x.source_file)), ...r.unassigned]);
+      setDuplicateGroups(r.duplicates);
+      if (r.session_id) {
+        setDashboardData(processData(r.session_id, r.statements));
+      }
+    });
+    const unlistenProgress = listen("progress", (event) => {
+      const p = event.payload;
+      // 任务结束或阶段完成后清除进度 (总数未知的阶段持续显示到下一阶段开始或任务结束)
+      setProgress(p.step === "done" || (p.total > 0 && p.current >= p.total) ? null : p);
+    });
     return () => {
       unlisten.then((fn) => fn());
+      unlistenProgress.then((fn) => fn());
+      unlistenIngest.then((fn) => fn());
     };
   }, []);
 
@@ -101,40 +157,25 @@ function App() {
     }
   };
 
-  const processData = (items) => {
+  // 后端返回按客户和对账单分组的汇总，明细按需查询
+  const processData = (sessionId, statements) => {
     const map = {};
     const customerLastDate = {};
     const customerTypeMap = {};
 
-    items.forEach((item) => {
-      if (!item.customer) return;
-
+    statements.forEach((s) => {
       // 记录最后日期用于排序
-      if (!customerLastDate[item.customer] || item.date > customerLastDate[item.customer]) {
-        customerLastDate[item.customer] = item.date;
+      if (!customerLastDate[s.customer] || s.last_date > customerLastDate[s.customer]) {
+        customerLastDate[s.customer] = s.last_date;
       }
 
       // 记录客户类型
-      if (!customerTypeMap[item.customer]) {
-        customerTypeMap[item.customer] = item.customer_type;
-      }
-
-      // 解析年月 YYYY-MM
-      let month = "未知";
-      try {
-        const dateParts = item.date.split(/[-/]/);
-        if (dateParts.length >= 2) {
-          month = `${dateParts[0]}年${parseInt(dateParts[1])}月`;
-        } else {
-          month = "其他日期";
-        }
-      } catch (e) {
-        month = "日期格式错误";
+      if (!customerTypeMap[s.customer]) {
+        customerTypeMap[s.customer] = s.customer_type;
       }
 
-      if (!map[item.customer]) map[item.customer] = {};
-      if (!map[item.customer][month]) map[item.customer][month] = [];
-      map[item.customer][month].push(item);
+      if (!map[s.customer]) map[s.customer] = {};
+      map[s.customer][s.statement_group || "未知"] = s;
     });
 
     // 按最近日期排序客户
@@ -143,20 +184,105 @@ function App() {
     });
 
     return {
+      sessionId,
       map,
       customers: sortedCustomers,
       customerTypeMap,
-      allItems: items,
     };
   };
 
+  // 查询扫描会话中的明细 (limit 为 0 时返回全部)
+  const queryItems = async (sessionId, query) => {
+    return await invoke("query_session_items", {
+      sessionId,
+      query: { offset: 0, limit: 0, ...query },
+    });
+  };
+
+  // 为日期无法识别的条目指定日期 (保存为源数据修正后重新扫描)
+  const assignDate = async (item) => {
+    const date = assignDates[`${item.source_file}#${item.source_row}`];
+    if (!date) return;
+    try {
+      await invoke("save_correction", {
+        correction: {
+          file: item.source_file,
+          row: item.source_row,
+          date,
+          note: `原日期: ${item.date}`,
+        },
+      });
+      addLog(`已指定日期: ${item.source_file.split(/[/\\]/).pop()} 第 ${item.source_row} 行 -> ${date}`, "success");
+      await scanAndValidate(config);
+    } catch (error) {
+      addLog(`指定日期失败: ${errorMessage(error)}`, "error");
+    }
+  };
+
+  const resolveDuplicate = async (group, keptFile) => {
+    try {
+      const newConfig = await invoke("resolve_duplicate", { key: group.key, keptFile });
+      setConfig(newConfig);
+      addLog(`已处理重复行: 保留 ${keptFile.split(/[/\\]/).pop()} 中的记录`, "success");
+      await scanAndValidate(newConfig);
+    } catch (error) {
+      addLog(`处理重复行失败: ${errorMessage(error)}`, "error");
+    }
+  };
+
+  // 设置有问题文件的处理方式 (接受有效行 / 排除整个文件)，之后重新扫描
+  const setFileAction = async (file, action) => {
+    try {
+      const newConfig = await invoke("set_file_action", { file, action });
+      setConfig(newConfig);
+      const label = action === "exclude_file" ? "排除整个文件" : "接受有效行";
+      addLog(`${file.split(/[/\\]/).pop()}: ${label}`, "success");
+      await scanAndValidate(newConfig);
+    } catch (error) {
+      addLog(`设置文件处理方式失败: ${errorMessage(error)}`, "error");
+    }
+  };
+
+  const updateRowFix = (key, field, value) => {
+    setRowFixes({ ...rowFixes, [key]: { ...rowFixes[key], [field]: value } });
+  };
+
+  // 修正被拒绝的行 (保存为源数据修正后重新扫描)
+  const fixRejectedRow = async (rejected) => {
+    const key = `${rejected.file}#${rejected.row}`;
+    const fix = rowFixes[key] || {};
+    const correction = { file: rejected.file, row: rejected.row, note: rejected.reasons.join("；") };
+    if (fix.date) correction.date = fix.date;
+    if (fix.quantity) correction.quantity = Number(fix.quantity);
+    if (fix.unit_price) correction.unit_price = Number(fix.unit_price);
+    try {
+      await invoke("save_correction", { correction });
+      addLog(`已修正: ${rejected.file.split(/[/\\]/).pop()} 第 ${rejected.row} 行`, "success");
+      const { [key]: _, ...rest } = rowFixes;
+      setRowFixes(rest);
+      await scanAndValidate(config);
+    } catch (error) {
+      addLog(`修正失败: ${errorMessage(error)}`, "error");
+    }
+  };
+
+  const cancelJob = async () => {
+    if (!currentJobId) return;
+    await invoke("cancel_job", { jobId: currentJobId });
+    addLog("正在取消，当前文件处理完后停止...", "warning");
+  };
+
   const scanAndValidate = async (currentConfig) => {
     setIsLoadingData(true);
     try {
       addLog("正在自动扫描并验证原始数据...", "info");
-      const result = await invoke("scan_and_validate", { config: currentConfig });
+      const jobId = `scan-${Date.now()}`;
+      setCurrentJobId(jobId);
+      const result = await invoke("scan_and_validate", { config: currentConfig, jobId });
 
-      if (result.success) {
+      if (result.cancelled) {
+        addLog(result.message, "warning");
+      } else if (result.success) {
         addLog(`数据验证通过: 找到 ${result.total_files} 个文件`, "success");
       } else {
         addLog(`数据验证发现问题: ${result.message}`, "error");
@@ -179,9 +305,26 @@ function App() {
         setShowLogs(true);
       }
 
+      const changes = result.file_changes;
+      if (changes && (changes.added.length || changes.changed.length || changes.removed.length)) {
+        addLog(`文件变化: 新增 ${changes.added.length} 个，修改 ${changes.changed.length} 个，删除 ${changes.removed.length} 个`, "info");
+      }
+
+      setUnassignedItems(result.unassigned || []);
+      if (result.unassigned && result.unassigned.length > 0) {
+        addLog(`有 ${result.unassigned.length} 行日期无法识别，请在“日期待指定”中指定日期`, "warning");
+      }
+
+      setDuplicateGroups(result.duplicates || []);
+
+      setRejectedRows(result.rejected_rows || []);
+      if (result.rejected_rows && result.rejected_rows.length > 0) {
+        addLog(`有 ${result.rejected_rows.length} 行因错误被拒绝，请在“被拒绝的行”中修正或选择文件处理方式`, "warning");
+      }
+
       // 处理用于显示的数据
-      if (result.items) {
-        const processed = processData(result.items);
+      if (result.session_id) {
+        const processed = processData(result.session_id, result.statements || []);
         setDashboardData(processed);
         if (processed.customers.length > 0 && !selectedCustomer) {
             // 默认不用选中，或者可以选中第一个
@@ -191,9 +334,11 @@ function App() {
 
     } catch (error) {
       console.error("扫描失败:", error);
-      addLog(`扫描失败: ${error}`, "error");
+      addLog(`扫描失败: ${errorMessage(error)}`, "error");
     } finally {
       setIsLoadingData(false);
+      setCurrentJobId(null);
+      setProgress(null);
     }
   };
 
@@ -202,9 +347,15 @@ function App() {
     try {
       await invoke("save_config", { config: newConfig });
       // 配置变更后重新扫描
-      if (newConfig.raw_data_path !== config.raw_data_path) {
+      if (newConfig.raw_data_path !== config.raw_data_path || newConfig.include_archived !== config.include_archived) {
           scanAndValidate(newConfig);
       }
+      // 自动导入: 按新配置重新开始或停止监视
+      if (newConfig.auto_ingest && newConfig.raw_data_path) {
+          await invoke("start_watching", { config: newConfig });
+      } else if (config.auto_ingest) {
+          await invoke("stop_watching");
+      }
     } catch (error) {
       console.error("保存配置失败:", error);
     }
@@ -227,7 +378,7 @@ function App() {
       }
     } catch (error) {
       console.error("选择文件夹失败:", error);
-      addLog(`选择文件夹失败: ${error}`, "error");
+      addLog(`选择文件夹失败: ${errorMessage(error)}`, "error");
     }
   };
 
@@ -241,40 +392,40 @@ function App() {
         return;
     }
 
-    const items = dashboardData.map[selectedCustomer]?.[selectedMonth] || [];
-    if (items.length === 0) {
+    if (!dashboardData.map[selectedCustomer]?.[selectedMonth]) {
         addLog("当前选择无数据", "error");
         return;
     }
 
     setIsProcessing(true);
 
-    const callGenerate = async (overwrite = false) => {
+    // policy 为空时使用设置中的覆盖策略
+    const callGenerate = async (policy = null) => {
         const result = await invoke("generate_single_statement", {
             config,
-            items,
+            sessionId: dashboardData.sessionId,
             customer: selectedCustomer,
             month: selectedMonth,
-            overwrite
+            policy
         });
 
-        if (result.success) {
+        const outcome = result.statements[0];
+        if (outcome?.action === "skipped") {
+            addLog(result.message, "info");
+        } else if (result.success) {
             addLog(result.message, "success");
-            const filePath = result.message.replace("已生成: ", "");
-            setGeneratedFilePath(filePath);
+            setGeneratedFilePath(outcome.file);
             setSuccessModalOpen(true);
         }
+        return outcome;
     };
 
     try {
       addLog(`开始生成对账单: ${selectedCustomer} ${selectedMonth}...`, "info");
-      await callGenerate(false);
-    } catch (error) {
-      // Normalize error message
-      const errorStr = typeof error === 'string' ? error : (error?.message || JSON.stringify(error));
-
-      if (errorStr.includes("FILE_EXISTS")) {
-          const confirmed = await ask(`对账单已存在，是否覆盖？\n\n客户: ${selectedCustomer}\n月份: ${selectedMonth}`, {
+      const outcome = await callGenerate();
+      // 按覆盖策略跳过时由用户确认是否覆盖
+      if (outcome?.action === "skipped") {
+          const confirmed = await ask(`${outcome.reason}，是否覆盖？\n\n客户: ${selectedCustomer}\n月份: ${selectedMonth}`, {
               title: "确认覆盖",
               type: "warning",
               okLabel: "覆盖",
@@ -282,19 +433,14 @@ function App() {
           });
 
           if (confirmed) {
-              try {
-                  await callGenerate(true);
-              } catch (retryError) {
-                  console.error("覆盖生成失败:", retryError);
-                  addLog(`覆盖生成失败: ${retryError}`, "error");
-              }
+              await callGenerate("overwrite");
           } else {
               addLog("已取消生成", "info");
           }
-      } else {
-          console.error("生成失败:", error);
-          addLog(`生成失败: ${errorStr}`, "error");
       }
+    } catch (error) {
+      console.error("生成失败:", error);
+      addLog(`生成失败: ${errorMessage(error)}`, "error");
     } finally {
       setIsProcessing(false);
     }
@@ -306,7 +452,7 @@ function App() {
         await openPath(config.output_path);
       } catch (error) {
         console.error("打开文件夹失败:", error);
-        addLog(`打开文件夹失败: ${error}`, "error");
+        addLog(`打开文件夹失败: ${errorMessage(error)}`, "error");
       }
     }
   };
@@ -318,10 +464,28 @@ function App() {
       await openPath(path);
     } catch (error) {
       console.error("打开文件失败:", error);
-      addLog(`打开文件失败: ${error}`, "error");
+      addLog(`打开文件失败: ${errorMessage(error)}`, "error");
     }
   };
 
+  // 有被拒绝行或已设置处理方式的文件
+  const problemFiles = useMemo(() => {
+    const fileActions = config.file_actions || [];
+    const byFile = {};
+    rejectedRows.forEach((r) => {
+      if (!byFile[r.file]) byFile[r.file] = [];
+      byFile[r.file].push(r);
+    });
+    fileActions.forEach((a) => {
+      if (!byFile[a.file]) byFile[a.file] = [];
+    });
+    return Object.entries(byFile).map(([file, rows]) => ({
+      file,
+      rows,
+      action: fileActions.find((a) => a.file === file)?.action || "accept_valid_rows",
+    }));
+  }, [rejectedRows, config.file_actions]);
+
   const filteredCustomers = useMemo(() => {
     let list = dashboardData.customers;
 
@@ -364,26 +528,68 @@ function App() {
     }
   }, [customerMonths]);
 
-  const currentItems = useMemo(() => {
-    if (!selectedCustomer || !selectedMonth) return [];
-    return dashboardData.map[selectedCustomer]?.[selectedMonth] || [];
-  }, [selectedCustomer, selectedMonth, dashboardData.map]);
+  // 切换客户、月份或筛选条件后回到第一页
+  useEffect(() => {
+    setItemOffset(0);
+  }, [selectedCustomer, selectedMonth, itemFilter, itemSort]);
+
+  // 选中客户和月份后从后端分页查询明细
+  useEffect(() => {
+    if (!dashboardData.sessionId || !selectedCustomer || !selectedMonth
+        || !dashboardData.map[selectedCustomer]?.[selectedMonth]) {
+      setCurrentItems([]);
+      setItemTotals({ total: 0, quantity: 0, amount: 0 });
+      return;
+    }
+    let stale = false;
+    queryItems(dashboardData.sessionId, {
+      customer: selectedCustomer,
+      statement_group: selectedMonth,
+      product: itemFilter.product || null,
+      delivery_order_no: itemFilter.delivery_order_no || null,
+      status: itemFilter.status || null,
+      sort_by: itemSort.sort_by,
+      descending: itemSort.descending,
+      offset: itemOffset,
+      limit: ITEM_PAGE_SIZE,
+    })
+      .then((page) => {
+        if (stale) return;
+        setCurrentItems(page.items);
+        setItemTotals({ total: page.total, quantity: page.total_quantity, amount: page.total_amount });
+      })
+      .catch((error) => addLog(`加载明细失败: ${errorMessage(error)}`, "error"));
+    return () => { stale = true; };
+  }, [selectedCustomer, selectedMonth, dashboardData, itemFilter, itemSort, itemOffset]);
+
+  // 点击表头排序: 同一列再次点击切换升降序
+  const toggleSort = (field) => {
+    setItemSort((prev) => prev.sort_by === field
+      ? { sort_by: field, descending: !prev.descending }
+      : { sort_by: field, descending: false });
+  };
+
+  const sortMark = (field) => itemSort.sort_by === field ? (itemSort.descending ? " ↓" : " ↑") : "";
+
+  // 分析视图: 查询分析对象的明细
+  useEffect(() => {
+    if (currentView !== "analysis" || !dashboardData.sessionId) {
+      setAnalysisItems([]);
+      return;
+    }
+    let stale = false;
+    const query = analysisTarget === "all" ? {} : { customer: analysisTarget };
+    queryItems(dashboardData.sessionId, query)
+      .then((page) => { if (!stale) setAnalysisItems(page.items); })
+      .catch((error) => addLog(`加载分析数据失败: ${errorMessage(error)}`, "error"));
+    return () => { stale = true; };
+  }, [currentView, analysisTarget, dashboardData.sessionId]);
 
-  const currentSummary = useMemo(() => {
-    return currentItems.reduce((acc, item) => ({
-      quantity: acc.quantity + item.quantity,
-      amount: acc.amount + item.amount
-    }), { quantity: 0, amount: 0 });
-  }, [currentItems]);
 
   // --- 分析数据计算 ---
   const analysisData = useMemo(() => {
-    if (!dashboardData.allItems || dashboardData.allItems.length === 0) return null;
-
-    // 1. 过滤数据
-    const items = analysisTarget === 'all'
-        ? dashboardData.allItems
-        : dashboardData.allItems.filter(i => i.customer === analysisTarget);
+    // 1. 分析对象的数据 (已按分析对象从后端查询)
+    const items = analysisItems;
 
     if (items.length === 0) return null;
 
@@ -489,7 +695,7 @@ function App() {
     }
 
     return { totalAmount, totalQuantity, totalOrders, monthlyTrend, rankingTitle, rankingData, rankingType, productTrends };
-  }, [dashboardData.allItems, analysisTarget]);
+  }, [analysisItems, analysisTarget]);
 
 
   return (
@@ -586,6 +792,41 @@ function App() {
                     </div>
                   </div>
 
+                  {/* 自动导入 */}
+                  <div>
+                    <label className="flex items-center gap-2 text-sm text-slate-600">
+                      <input
+                        type="checkbox"
+                        checked={!!config.auto_ingest}
+                        onChange={(e) => saveConfig({ ...config, auto_ingest: e.target.checked })}
+                      />
+                      监视原始数据文件夹，自动导入新的送货单
+                    </label>
+                    <label className="flex items-center gap-2 text-sm text-slate-600 mt-2">
+                      <input
+                        type="checkbox"
+                        checked={config.include_archived !== false}
+                        onChange={(e) => saveConfig({ ...config, include_archived: e.target.checked })}
+                      />
+                      对账包括源文件已归档的历史记录
+                    </label>
+                  </div>
+
+                  {/* 覆盖策略 */}
+                  <div>
+                    <label className="block text-sm text-slate-600 mb-1.5">对账单已存在时</label>
+                    <select
+                      value={config.overwrite_policy || "skip"}
+                      onChange={(e) => saveConfig({ ...config, overwrite_policy: e.target.value })}
+                      className="w-full px-3 py-2 border border-slate-300 rounded-lg text-sm"
+                    >
+                      <option value="skip">跳过 (单个生成时询问是否覆盖)</option>
+                      <option value="overwrite">覆盖</option>
+                      <option value="overwrite_if_changed">源数据变化时覆盖</option>
+                      <option value="versioned">另存为新版本 (_v2)</option>
+                    </select>
+                  </div>
+
                   {/* 公司信息 */}
                   <div>
                     <h3 className="text-sm font-medium text-slate-900 mb-3">公司信息</h3>
@@ -742,9 +983,8 @@ function App() {
                           </div>
                           <span className="truncate flex-1">{customer}</span>
                           {(() => {
-                              const months = Object.keys(dashboardData.map[customer] || {});
-                              if (months.length > 0 && dashboardData.map[customer][months[0]].length > 0) {
-                                  const type = dashboardData.map[customer][months[0]][0].customer_type;
+                              const type = dashboardData.customerTypeMap[customer];
+                              if (type) {
                                   // 默认不显示 "默认" 类型
                                   if (type === "默认") return null;
 
@@ -866,9 +1106,8 @@ function App() {
                             </div>
                             <span className="truncate flex-1">{customer}</span>
                             {(() => {
-                              const months = Object.keys(dashboardData.map[customer] || {});
-                              if (months.length > 0 && dashboardData.map[customer][months[0]].length > 0) {
-                                  const type = dashboardData.map[customer][months[0]][0].customer_type;
+                              const type = dashboardData.customerTypeMap[customer];
+                              if (type) {
                                   // 默认不显示 "默认" 类型
                                   if (type === "默认") return null;
 
@@ -951,20 +1190,68 @@ function App() {
                                       </div>
                                       {/* Data Table */}
                    <div className="flex-1 overflow-auto p-6">
+                     {/* 明细筛选 */}
+                     <div className="flex items-center gap-2 mb-3 text-sm">
+                       <input
+                         type="text"
+                         placeholder="货名/规格"
+                         value={itemFilter.product}
+                         onChange={(e) => setItemFilter({ ...itemFilter, product: e.target.value })}
+                         className="px-3 py-1.5 border border-slate-200 rounded-lg"
+                       />
+                       <input
+                         type="text"
+                         placeholder="送货单号"
+                         value={itemFilter.delivery_order_no}
+                         onChange={(e) => setItemFilter({ ...itemFilter, delivery_order_no: e.target.value })}
+                         className="px-3 py-1.5 border border-slate-200 rounded-lg"
+                       />
+                       <select
+                         value={itemFilter.status}
+                         onChange={(e) => setItemFilter({ ...itemFilter, status: e.target.value })}
+                         className="px-3 py-1.5 border border-slate-200 rounded-lg"
+                       >
+                         <option value="">全部状态</option>
+                         {Object.entries(ITEM_STATUS_LABELS).map(([value, label]) => (
+                           <option key={value} value={value}>{label}</option>
+                         ))}
+                       </select>
+                       {itemTotals.total > ITEM_PAGE_SIZE && (
+                         <div className="ml-auto flex items-center gap-2 text-slate-500">
+                           <button
+                             onClick={() => setItemOffset(Math.max(0, itemOffset - ITEM_PAGE_SIZE))}
+                             disabled={itemOffset === 0}
+                             className="px-2 py-1 border border-slate-200 rounded disabled:opacity-50"
+                           >
+                             上一页
+                           </button>
+                           <span>
+                             {itemOffset + 1}-{Math.min(itemOffset + ITEM_PAGE_SIZE, itemTotals.total)} / {itemTotals.total}
+                           </span>
+                           <button
+                             onClick={() => setItemOffset(itemOffset + ITEM_PAGE_SIZE)}
+                             disabled={itemOffset + ITEM_PAGE_SIZE >= itemTotals.total}
+                             className="px-2 py-1 border border-slate-200 rounded disabled:opacity-50"
+                           >
+                             下一页
+                           </button>
+                         </div>
+                       )}
+                     </div>
                      <div className="bg-white rounded-xl border border-slate-200 shadow-sm overflow-hidden">
                        <div className="overflow-x-auto">
                         <table className="w-full text-sm text-left">
                           <thead className="bg-slate-50 border-b border-slate-200 text-slate-500 font-medium">
                             <tr>
-                              <th className="px-4 py-3 w-32">日期</th>
-                              <th className="px-4 py-3 w-32">送货单号</th>
-                              <th className="px-4 py-3 w-32">订单号</th>
-                              <th className="px-4 py-3">货名</th>
+                              <th className="px-4 py-3 w-32 cursor-pointer" onClick={() => toggleSort("date")}>日期{sortMark("date")}</th>
+                              <th className="px-4 py-3 w-32 cursor-pointer" onClick={() => toggleSort("delivery_order_no")}>送货单号{sortMark("delivery_order_no")}</th>
+                              <th className="px-4 py-3 w-32 cursor-pointer" onClick={() => toggleSort("order_no")}>订单号{sortMark("order_no")}</th>
+                              <th className="px-4 py-3 cursor-pointer" onClick={() => toggleSort("product_name")}>货名{sortMark("product_name")}</th>
                               <th className="px-4 py-3 w-24">规格</th>
-                              <th className="px-4 py-3 w-20 text-right">数量</th>
+                              <th className="px-4 py-3 w-20 text-right cursor-pointer" onClick={() => toggleSort("quantity")}>数量{sortMark("quantity")}</th>
                               <th className="px-4 py-3 w-16 text-center">单位</th>
-                              <th className="px-4 py-3 w-24 text-right">单价</th>
-                              <th className="px-4 py-3 w-30 text-right">金额</th>
+                              <th className="px-4 py-3 w-24 text-right cursor-pointer" onClick={() => toggleSort("unit_price")}>单价{sortMark("unit_price")}</th>
+                              <th className="px-4 py-3 w-30 text-right cursor-pointer" onClick={() => toggleSort("amount")}>金额{sortMark("amount")}</th>
                               <th className="px-4 py-3 w-42 text-slate-400 font-normal">来源</th>
                             </tr>
                           </thead>
@@ -978,7 +1265,14 @@ function App() {
                             ) : (
                               currentItems.map((item, idx) => (
                                 <tr key={idx} className="hover:bg-slate-50">
-                                  <td className="px-4 py-3 text-slate-600">{item.date}</td>
+                                  <td className="px-4 py-3 text-slate-600">
+                                    {item.date}
+                                    {item.status !== "ok" && (
+                                      <span className={`ml-1 text-[10px] px-1 rounded ${item.status === "duplicate" || item.status === "warning" ? "bg-amber-50 text-amber-600" : "bg-blue-50 text-blue-600"}`}>
+                                        {ITEM_STATUS_LABELS[item.status]}
+                                      </span>
+                                    )}
+                                  </td>
                                   <td className="px-4 py-3 text-slate-600">{item.delivery_order_no}</td>
                                   <td className="px-4 py-3 text-slate-600 font-mono text-xs">{item.order_no}</td>
                                   <td
@@ -1009,11 +1303,11 @@ function App() {
                           {currentItems.length > 0 && (
                             <tfoot className="bg-slate-50 border-t border-slate-200 font-semibold text-slate-900">
                               <tr>
-                                <td colSpan="5" className="px-4 py-3 text-right">本月合计:</td>
-                                <td className="px-4 py-3 text-right">{currentSummary.quantity}</td>
+                                <td colSpan="5" className="px-4 py-3 text-right">合计 ({itemTotals.total} 条):</td>
+                                <td className="px-4 py-3 text-right">{itemTotals.quantity}</td>
                                 <td className="px-4 py-3"></td>
                                 <td className="px-4 py-3 text-right"></td>
-                                <td className="px-4 py-3 text-right">¥{currentSummary.amount.toFixed(2)}</td>
+                                <td className="px-4 py-3 text-right">¥{itemTotals.amount.toFixed(2)}</td>
                                 <td className="px-4 py-3"></td>
                               </tr>
                             </tfoot>
@@ -1242,6 +1536,161 @@ function App() {
              </div>
            )}
 
+           {/* Unassigned Dates Panel */}
+           {currentView === "preview" && unassignedItems.length > 0 && (
+             <div className="bg-amber-50 border-t border-amber-200 px-6 py-3 max-h-48 overflow-y-auto flex-shrink-0">
+               <div className="text-sm font-medium text-amber-800 mb-2 flex items-center gap-2">
+                 <AlertCircle className="w-4 h-4" />
+                 日期待指定 ({unassignedItems.length} 行，指定前不会生成对账单)
+               </div>
+               <div className="space-y-1">
+                 {unassignedItems.map((item) => {
+                   const key = `${item.source_file}#${item.source_row}`;
+                   return (
+                     <div key={key} className="flex items-center gap-3 text-xs text-slate-700">
+                       <span className="truncate w-48" title={item.source_file}>
+                         {item.source_file.split(/[/\\]/).pop()} 第 {item.source_row} 行
+                       </span>
+                       <span className="truncate w-32">{item.customer}</span>
+                       <span className="truncate flex-1">{item.product_name} {item.spec}</span>
+                       <span className="text-red-500 w-28 truncate" title={item.date}>{item.date || "(空)"}</span>
+                       <input
+                         type="date"
+                         value={assignDates[key] || ""}
+                         onChange={(e) => setAssignDates({ ...assignDates, [key]: e.target.value })}
+                         className="px-2 py-0.5 border border-slate-300 rounded"
+                       />
+                       <button
+                         onClick={() => assignDate(item)}
+                         disabled={!assignDates[key]}
+                         className="px-2 py-0.5 bg-amber-500 hover:bg-amber-600 disabled:bg-slate-300 text-white rounded"
+                       >
+                         指定
+                       </button>
+                     </div>
+                   );
+                 })}
+               </div>
+             </div>
+           )}
+
+           {/* Rejected Rows Panel */}
+           {currentView === "preview" && problemFiles.length > 0 && (
+             <div className="bg-orange-50 border-t border-orange-200 px-6 py-3 max-h-60 overflow-y-auto flex-shrink-0">
+               <div className="text-sm font-medium text-orange-800 mb-2 flex items-center gap-2">
+                 <AlertCircle className="w-4 h-4" />
+                 被拒绝的行 ({rejectedRows.length} 行，修正后重新扫描，或按文件选择处理方式)
+               </div>
+               <div className="space-y-2">
+                 {problemFiles.map(({ file, rows, action }) => (
+                   <div key={file} className="text-xs text-slate-700">
+                     <div className="flex items-center gap-2 mb-1">
+                       <span className="flex-1 font-medium truncate" title={file}>
+                         {file.split(/[/\\]/).pop()}
+                       </span>
+                       <select
+                         value={action}
+                         onChange={(e) => setFileAction(file, e.target.value)}
+                         className="px-2 py-0.5 border border-slate-300 rounded bg-white"
+                       >
+                         <option value="accept_valid_rows">接受有效行</option>
+                         <option value="exclude_file">排除整个文件</option>
+                       </select>
+                     </div>
+                     {action === "exclude_file" ? (
+                       <div className="pl-2 text-slate-400">已排除，文件中的所有行都不参与生成</div>
+                     ) : (
+                       rows.map((r) => {
+                         const key = `${r.file}#${r.row}`;
+                         const fix = rowFixes[key] || {};
+                         return (
+                           <div key={key} className="flex items-center gap-2 pl-2 py-0.5">
+                             <span className="w-14 flex-shrink-0">第 {r.row} 行</span>
+                             <span className="truncate w-40">{r.item.product_name} {r.item.spec}</span>
+                             <span className="text-red-500 truncate flex-1" title={r.reasons.join("\n")}>
+                               {r.reasons.join("；")}
+                             </span>
+                             <input
+                               type="date"
+                               value={fix.date || ""}
+                               onChange={(e) => updateRowFix(key, "date", e.target.value)}
+                               className="px-2 py-0.5 border border-slate-300 rounded"
+                             />
+                             <input
+                               type="number"
+                               step="any"
+                               placeholder="数量"
+                               value={fix.quantity || ""}
+                               onChange={(e) => updateRowFix(key, "quantity", e.target.value)}
+                               className="w-20 px-2 py-0.5 border border-slate-300 rounded"
+                             />
+                             <input
+                               type="number"
+                               step="any"
+                               placeholder="单价"
+                               value={fix.unit_price || ""}
+                               onChange={(e) => updateRowFix(key, "unit_price", e.target.value)}
+                               className="w-20 px-2 py-0.5 border border-slate-300 rounded"
+                             />
+                             <button
+                               onClick={() => fixRejectedRow(r)}
+                               disabled={!fix.date && !fix.quantity && !fix.unit_price}
+                               className="px-2 py-0.5 bg-orange-500 hover:bg-orange-600 disabled:bg-slate-300 text-white rounded"
+                             >
+                               修正
+                             </button>
+                           </div>
+                         );
+                       })
+                     )}
+                   </div>
+                 ))}
+               </div>
+             </div>
+           )}
+
+           {/* Duplicate Lines Panel */}
+           {currentView === "preview" && duplicateGroups.some((g) => !g.resolved) && (
+             <div className="bg-rose-50 border-t border-rose-200 px-6 py-3 max-h-60 overflow-y-auto flex-shrink-0">
+               <div className="text-sm font-medium text-rose-800 mb-2 flex items-center gap-2">
+                 <AlertCircle className="w-4 h-4" />
+                 跨文件重复 ({duplicateGroups.filter((g) => !g.resolved).length} 组，请选择保留哪个文件中的记录)
+               </div>
+               <div className="space-y-2">
+                 {duplicateGroups.filter((g) => !g.resolved).map((group) => (
+                   <div key={group.key} className="text-xs text-slate-700">
+                     <div className="mb-1 font-medium">
+                       {group.customer} · {group.date} · {group.product_name} {group.spec} · 数量 {group.quantity} · 金额 {group.amount}
+                     </div>
+                     <div className="grid grid-cols-2 gap-2">
+                       {group.entries.map((entry) => (
+                         <div
+                           key={`${entry.file}#${entry.row}`}
+                           className={`flex items-center gap-2 px-2 py-1 rounded border ${entry.kept ? "border-emerald-300 bg-white" : "border-slate-200 bg-slate-50"}`}
+                         >
+                           <div className="flex-1 min-w-0">
+                             <div className="truncate" title={entry.file}>
+                               {entry.file.split(/[/\\]/).pop()} 第 {entry.row} 行
+                             </div>
+                             <div className="text-slate-400 truncate">
+                               送货单号 {entry.delivery_order_no || "(空)"} · 修改于 {entry.modified}
+                             </div>
+                           </div>
+                           <button
+                             onClick={() => resolveDuplicate(group, entry.file)}
+                             className="px-2 py-0.5 bg-rose-500 hover:bg-rose-600 text-white rounded flex-shrink-0"
+                           >
+                             保留此文件
+                           </button>
+                         </div>
+                       ))}
+                     </div>
+                   </div>
+                 ))}
+               </div>
+             </div>
+           )}
+
            {/* Logs Panel */}
            <div className={`bg-slate-900 flex-shrink-0 border-t border-slate-800 transition-all duration-300 flex flex-col ${showLogs ? "h-48" : "h-9"}`}>
               <div
@@ -1254,8 +1703,26 @@ function App() {
                      logs[logs.length-1].level === 'warning' ? <AlertCircle className="w-3 h-3 text-amber-400" /> :
                      <Check className="w-3 h-3 text-emerald-400" />
                    )}
-                   {logs.length > 0 ? logs[logs.length-1].message : "就绪"}
+                   {progress
+                     ? `${progress.message}${progress.eta_seconds != null ? ` (剩余约 ${Math.ceil(progress.eta_seconds)} 秒)` : ""}`
+                     : logs.length > 0 ? logs[logs.length-1].message : "就绪"}
                 </span>
+                {progress && progress.total > 0 && (
+                  <div className="w-40 h-1.5 bg-slate-700 rounded-full overflow-hidden flex-shrink-0 mr-4">
+                    <div
+                      className="h-full bg-emerald-400 transition-all"
+                      style={{ width: `${(progress.current / progress.total) * 100}%` }}
+                    />
+                  </div>
+                )}
+                {currentJobId && (
+                  <button
+                    onClick={(e) => { e.stopPropagation(); cancelJob(); }}
+                    className="text-xs text-red-400 hover:text-red-300 flex-shrink-0 mr-4"
+                  >
+                    取消
+                  </button>
+                )}
                 <div className="flex items-center gap-3 flex-shrink-0">
                     <span className="text-xs text-slate-500 hover:text-slate-300">
                         {showLogs ? "收起" : "展开"}